@@ -0,0 +1,50 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use eee_hyst::generators::{rate_for_load, PacketSize, Poisson};
+use eee_hyst::simulator::{Simulator, Time};
+use eee_hyst::switch::Packet;
+use eee_hyst::trace::{TraceReader, TraceSliceReader};
+use std::fmt::Write;
+
+const PACKETS: usize = 100_000;
+
+/// Simulates Poisson traffic of increasing load, from mostly idle links
+/// that enter and leave LPI for nearly every packet to busy ones.
+fn throughput(c: &mut Criterion) {
+    let mut group = c.benchmark_group("simulator");
+    group.throughput(Throughput::Elements(PACKETS as u64));
+
+    for load in [0.01, 0.1, 0.5] {
+        // Generate the traffic beforehand so that only the simulator is measured
+        let rate = rate_for_load(load, 10e9, PacketSize::Imix);
+        let input: Vec<Packet> = Poisson::new(rate, PacketSize::Imix, 1)
+            .take(PACKETS)
+            .collect();
+
+        group.bench_with_input(BenchmarkId::new("poisson", load), &input, |b, input| {
+            b.iter(|| Simulator::new(Time(1000), Time(0), input.iter().copied()).count())
+        });
+    }
+
+    group.finish();
+}
+
+/// Parses a trace in the format written by the simulator.
+fn parse(c: &mut Criterion) {
+    let mut trace = String::new();
+    for packet in Poisson::new(1e6, PacketSize::Imix, 1).take(PACKETS) {
+        writeln!(trace, "{:e}\t{}", packet.arrival().as_secs(), packet.size()).unwrap();
+    }
+
+    let mut group = c.benchmark_group("parser");
+    group.throughput(Throughput::Elements(PACKETS as u64));
+    group.bench_function("read", |b| {
+        b.iter(|| TraceReader::new(trace.as_bytes()).count())
+    });
+    group.bench_function("slice", |b| {
+        b.iter(|| TraceSliceReader::new(trace.as_bytes()).count())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, throughput, parse);
+criterion_main!(benches);
@@ -0,0 +1,281 @@
+use crate::simulator::Time;
+use crate::switch::Packet;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Exp, Pareto};
+use std::str::FromStr;
+
+/// Simple IMIX: sizes in bytes with their relative weights.
+const IMIX: [(u32, u32); 3] = [(64, 7), (576, 4), (1500, 1)];
+
+/// Size of the generated packets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PacketSize {
+    /// Every packet has the same size in bytes.
+    Fixed(u32),
+    /// 64, 576 and 1500 byte packets in a 7:4:1 proportion.
+    Imix,
+}
+
+impl PacketSize {
+    /// Mean packet size in bytes.
+    pub fn mean(self) -> f64 {
+        match self {
+            PacketSize::Fixed(size) => f64::from(size),
+            PacketSize::Imix => {
+                let total: u32 = IMIX.iter().map(|(_, weight)| weight).sum();
+                IMIX.iter()
+                    .map(|(size, weight)| f64::from(size * weight))
+                    .sum::<f64>()
+                    / f64::from(total)
+            }
+        }
+    }
+
+    fn sample<R: Rng>(self, rng: &mut R) -> u32 {
+        match self {
+            PacketSize::Fixed(size) => size,
+            PacketSize::Imix => {
+                let total: u32 = IMIX.iter().map(|(_, weight)| weight).sum();
+                let mut pick = rng.gen_range(0..total);
+                for (size, weight) in IMIX {
+                    if pick < weight {
+                        return size;
+                    }
+                    pick -= weight;
+                }
+                unreachable!()
+            }
+        }
+    }
+}
+
+impl FromStr for PacketSize {
+    type Err = String;
+
+    /// Parses `"imix"` or a size in bytes.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("imix") {
+            return Ok(PacketSize::Imix);
+        }
+
+        match u32::from_str(s.trim()) {
+            Ok(size) if size > 0 => Ok(PacketSize::Fixed(size)),
+            _ => Err(format!(
+                "{} is not a valid packet size. Use a size in bytes or imix",
+                s
+            )),
+        }
+    }
+}
+
+/// Packet rate, in packets/s, that offers a `load` fraction of a link of
+/// `capacity` b/s with packets of `size`.
+pub fn rate_for_load(load: f64, capacity: f64, size: PacketSize) -> f64 {
+    load * capacity / (8.0 * size.mean())
+}
+
+/// Poisson arrivals of `rate` packets/s. Never ends.
+pub struct Poisson {
+    rng: StdRng,
+    interarrival: Exp<f64>,
+    size: PacketSize,
+    time: f64,
+}
+
+impl Poisson {
+    pub fn new(rate: f64, size: PacketSize, seed: u64) -> Poisson {
+        assert!(rate > 0.0, "The packet rate must be positive");
+
+        Poisson {
+            rng: StdRng::seed_from_u64(seed),
+            interarrival: Exp::new(rate).unwrap(),
+            size,
+            time: 0.0,
+        }
+    }
+}
+
+impl Iterator for Poisson {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        self.time += self.interarrival.sample(&mut self.rng);
+
+        Some(Packet::new(
+            Time::from_secs(self.time),
+            self.size.sample(&mut self.rng),
+        ))
+    }
+}
+
+/// Evenly spaced arrivals of `rate` packets/s, the first one at time zero.
+/// Only the packet sizes are random. Never ends.
+pub struct Cbr {
+    rng: StdRng,
+    rate: f64,
+    size: PacketSize,
+    count: u64,
+}
+
+impl Cbr {
+    pub fn new(rate: f64, size: PacketSize, seed: u64) -> Cbr {
+        assert!(rate > 0.0, "The packet rate must be positive");
+
+        Cbr {
+            rng: StdRng::seed_from_u64(seed),
+            rate,
+            size,
+            count: 0,
+        }
+    }
+}
+
+impl Iterator for Cbr {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        let arrival = Time::from_secs(self.count as f64 / self.rate);
+        self.count += 1;
+
+        Some(Packet::new(arrival, self.size.sample(&mut self.rng)))
+    }
+}
+
+/// Bursts of packets sent at `peak` packets/s separated by silences. Both
+/// the bursts and the silences last a Pareto distributed time. Silences
+/// are as long as needed for a mean rate of about `rate` packets/s. Never
+/// ends.
+pub struct OnOff {
+    rng: StdRng,
+    peak: f64,
+    on: Pareto<f64>,
+    off: Pareto<f64>,
+    size: PacketSize,
+    time: f64,
+    burst_end: f64,
+}
+
+impl OnOff {
+    /// `burst` is the mean length of the bursts and `shape` the shape
+    /// parameter of both Pareto distributions, which must be over one for
+    /// their mean to exist.
+    pub fn new(
+        rate: f64,
+        peak: f64,
+        burst: Time,
+        shape: f64,
+        size: PacketSize,
+        seed: u64,
+    ) -> OnOff {
+        assert!(rate > 0.0, "The packet rate must be positive");
+        assert!(peak > rate, "The peak rate must exceed the mean rate");
+        assert!(burst > Time(0), "Bursts must have a positive length");
+        assert!(shape > 1.0, "The Pareto shape must be over one");
+
+        let on = burst.as_secs();
+        let off = on * (peak / rate - 1.0);
+        let scale = (shape - 1.0) / shape;
+        let mut rng = StdRng::seed_from_u64(seed);
+        let on = Pareto::new(on * scale, shape).unwrap();
+        let burst_end = on.sample(&mut rng);
+
+        OnOff {
+            rng,
+            peak,
+            on,
+            off: Pareto::new(off * scale, shape).unwrap(),
+            size,
+            time: 0.0,
+            burst_end,
+        }
+    }
+}
+
+impl Iterator for OnOff {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        if self.time >= self.burst_end {
+            self.time = self.burst_end + self.off.sample(&mut self.rng);
+            self.burst_end = self.time + self.on.sample(&mut self.rng);
+        }
+
+        let arrival = Time::from_secs(self.time);
+        self.time += 1.0 / self.peak;
+
+        Some(Packet::new(arrival, self.size.sample(&mut self.rng)))
+    }
+}
+
+/// Two-state Markov-modulated Poisson process. In state `i` packets arrive
+/// as a Poisson process of `rates[i]` packets/s, and the process stays
+/// there an exponentially distributed time of mean `sojourns[i]`. Starts in
+/// the first state. Never ends.
+pub struct Mmpp {
+    rng: StdRng,
+    arrivals: [Exp<f64>; 2],
+    sojourns: [Exp<f64>; 2],
+    size: PacketSize,
+    state: usize,
+    time: f64,
+    state_end: f64,
+}
+
+impl Mmpp {
+    pub fn new(rates: [f64; 2], sojourns: [Time; 2], size: PacketSize, seed: u64) -> Mmpp {
+        assert!(
+            rates.iter().all(|rate| *rate >= 0.0) && rates.iter().any(|rate| *rate > 0.0),
+            "The packet rates must not be negative nor both zero"
+        );
+        assert!(
+            sojourns.iter().all(|sojourn| *sojourn > Time(0)),
+            "Sojourn times must be positive"
+        );
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let sojourns = sojourns.map(|sojourn| Exp::new(1.0 / sojourn.as_secs()).unwrap());
+        let state_end = sojourns[0].sample(&mut rng);
+
+        Mmpp {
+            rng,
+            arrivals: rates.map(|rate| Exp::new(rate).unwrap()),
+            sojourns,
+            size,
+            state: 0,
+            time: 0.0,
+            state_end,
+        }
+    }
+
+    /// Mean rate, in packets/s, of a process whose states have the given
+    /// rates and mean sojourn times.
+    pub fn mean_rate(rates: [f64; 2], sojourns: [Time; 2]) -> f64 {
+        (rates[0] * sojourns[0].as_secs() + rates[1] * sojourns[1].as_secs())
+            / (sojourns[0] + sojourns[1]).as_secs()
+    }
+}
+
+impl Iterator for Mmpp {
+    type Item = Packet;
+
+    fn next(&mut self) -> Option<Packet> {
+        loop {
+            // Exponential interarrivals are memoryless, so the arrival
+            // process simply restarts at every state change
+            let arrival = self.time + self.arrivals[self.state].sample(&mut self.rng);
+
+            if arrival < self.state_end {
+                self.time = arrival;
+                return Some(Packet::new(
+                    Time::from_secs(arrival),
+                    self.size.sample(&mut self.rng),
+                ));
+            }
+
+            self.time = self.state_end;
+            self.state = 1 - self.state;
+            self.state_end = self.time + self.sojourns[self.state].sample(&mut self.rng);
+        }
+    }
+}
@@ -1,4 +1,13 @@
+pub mod generators;
+pub mod output;
+pub mod pcap;
+pub mod phy;
+pub mod policy;
+pub mod power;
 pub mod simulator;
+pub mod stats;
+pub mod sweep;
 pub mod switch;
+pub mod trace;
 
 pub use crate::simulator::Time;
@@ -1,119 +1,1036 @@
-use clap::Parser;
-use eee_hyst::switch::{Packet, Status};
-use eee_hyst::{simulator, Time};
-use std::collections::HashMap;
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use eee_hyst::generators::{self, Cbr, Mmpp, OnOff, PacketSize, Poisson};
+use eee_hyst::output::{Format, RecordWriter, Value};
+use eee_hyst::pcap::{self, PcapReader, PcapWriter};
+use eee_hyst::phy::PhyProfile;
+use eee_hyst::policy::{
+    AdaptivePolicy, Coalescing, DeadlinePolicy, OraclePolicy, SleepPolicy, TimerPolicy,
+};
+use eee_hyst::power::{Power, PowerModel};
+use eee_hyst::simulator::{self, SimEvent};
+use eee_hyst::stats::{BreakdownStats, DelayStats, GapStats, QueueStats, StateStats, WakeStats};
+use eee_hyst::sweep::{self, Values};
+use eee_hyst::switch::{BufferLimit, Packet, Refresh, Status};
+#[cfg(feature = "mmap")]
+use eee_hyst::trace::{self, TraceSliceReader};
+use eee_hyst::trace::{TraceError, TraceReader};
+use eee_hyst::Time;
 use std::fs::File;
 use std::io;
 use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::iter::Iterator;
 use std::path::PathBuf;
+use std::thread;
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum PolicyKind {
+    /// Fixed hysteresis and delay
+    Fixed,
+    /// Hysteresis adapted to the observed idle periods
+    Adaptive,
+    /// Knows every arrival in advance and sleeps whenever that saves energy, adding no delay
+    Oracle,
+    /// Fixed hysteresis, leaving LPI as late as the deadline allows
+    Deadline,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum GeneratorKind {
+    /// Poisson arrivals
+    Poisson,
+    /// Constant bit rate
+    Cbr,
+    /// Pareto distributed bursts and silences
+    Onoff,
+    /// Two-state Markov-modulated Poisson process
+    Mmpp,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum FormatKind {
+    /// Tab separated values and a commented summary
+    Text,
+    /// Comma separated values with a header
+    Csv,
+    /// A JSON array of objects
+    Json,
+    /// A JSON object per line
+    Jsonl,
+}
+
+impl From<FormatKind> for Format {
+    fn from(kind: FormatKind) -> Format {
+        match kind {
+            FormatKind::Text => Format::Text,
+            FormatKind::Csv => Format::Csv,
+            FormatKind::Json => Format::Json,
+            FormatKind::Jsonl => Format::Jsonl,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ErrorAction {
+    /// Stop at the first error
+    Fail,
+    /// Ignore the offending lines
+    Skip,
+    /// Report the offending lines and ignore them
+    Warn,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum RefreshKind {
+    /// LPI is a single quiet period
+    Disabled,
+    /// Quiet and refresh periods alternate. Wake ups interrupt refreshes
+    Interruptible,
+    /// Quiet and refresh periods alternate. Wake ups wait for the refresh to finish
+    Blocking,
+}
+
+impl From<RefreshKind> for Refresh {
+    fn from(kind: RefreshKind) -> Refresh {
+        match kind {
+            RefreshKind::Disabled => Refresh::Disabled,
+            RefreshKind::Interruptible => Refresh::Interruptible,
+            RefreshKind::Blocking => Refresh::Blocking,
+        }
+    }
+}
 
 #[derive(Parser, Debug)]
-#[clap(author, version, about, long_about = None)]
+#[clap(author, version, about, long_about = None, disable_help_flag = true)]
 /// Miguel Rodríguez Pérez <miguel@det.uvigo.gal>
 /// A Rustified Simulator for 10Gb/s EEE with Configurable Hysteresis
 struct Opt {
-    /// Time before entering LPI in ns
+    /// Time before entering LPI, e.g. 10us. Plain numbers are ns
     #[clap(short = 'h', long = "hyst", default_value = "0")]
-    hyst: u64,
+    hyst: Time,
 
-    /// Time since firt scheduled packet in LPI until resuming normal mode in ns
+    /// Time since first scheduled packet in LPI until resuming normal mode, e.g. 10us. Plain numbers are ns
     #[clap(short = 'd', long = "delay", default_value = "0")]
-    delay: u64,
+    delay: Time,
+
+    /// PHY profile to simulate: 100base-tx, 1000base-t, 2.5gbase-t, 5gbase-t, 10gbase-t,
+    /// 10gbase-kr, 40gbase-kr4 or 100gbase-kr4
+    #[clap(long = "phy", default_value = "10gbase-t")]
+    phy: PhyProfile,
+
+    /// Override the time needed to enter LPI (Ts) of the PHY profile, e.g. 10us. Plain numbers are ns
+    #[clap(long = "ts")]
+    ts: Option<Time>,
+
+    /// Override the time needed to leave LPI (Tw) of the PHY profile, e.g. 10us. Plain numbers are ns
+    #[clap(long = "tw")]
+    tw: Option<Time>,
+
+    /// Override the line rate of the PHY profile in b/s
+    #[clap(long = "capacity")]
+    capacity: Option<f64>,
+
+    /// Power drawn when active, in W or as a percentage of the PHY profile active power
+    #[clap(long = "power-on")]
+    power_on: Option<Power>,
+
+    /// Power drawn in LPI, in W or as a percentage of the active power
+    #[clap(long = "power-off")]
+    power_off: Option<Power>,
+
+    /// Power drawn while leaving LPI, in W or as a percentage of the active power
+    #[clap(long = "power-ton")]
+    power_ton: Option<Power>,
+
+    /// Power drawn while entering LPI, in W or as a percentage of the active power
+    #[clap(long = "power-toff")]
+    power_toff: Option<Power>,
+
+    /// Model of the refresh periods inside LPI
+    #[clap(long = "refresh", value_enum, default_value = "disabled")]
+    refresh: RefreshKind,
+
+    /// Policy to decide when to enter LPI
+    #[clap(long = "policy", value_enum, default_value = "fixed")]
+    policy: PolicyKind,
+
+    /// Weight of every new idle period in the average of the adaptive
+    /// policy, greater than 0 and at most 1
+    #[clap(long = "alpha", default_value = "0.125", value_parser = alpha)]
+    alpha: f64,
+
+    /// Maximum delay of every packet under the deadline policy
+    #[clap(long = "deadline", required_if_eq("policy", "deadline"))]
+    deadline: Option<Time>,
+
+    /// Leave LPI before the delay expires once this many packets are queued
+    #[clap(long = "coalesce-packets")]
+    coalesce_packets: Option<usize>,
+
+    /// Leave LPI before the delay expires once this many bytes are queued
+    #[clap(long = "coalesce-bytes")]
+    coalesce_bytes: Option<u64>,
+
+    /// Transmit buffer size in packets. Unlimited if not present
+    #[clap(long = "buffer-packets")]
+    buffer_packets: Option<usize>,
+
+    /// Transmit buffer size in bytes. Unlimited if not present
+    #[clap(long = "buffer-bytes")]
+    buffer_bytes: Option<u64>,
 
-    /// Traffic input file to use. Format "time (s) length (bytes)". Leaveeee empty for STDIN
+    /// Power drawn during LPI refresh periods, in W or as a percentage of the active power
+    #[clap(long = "power-refresh")]
+    power_refresh: Option<Power>,
+
+    /// Simulate synthetic traffic instead of reading INPUT
+    #[clap(long = "generator", value_enum, conflicts_with = "INPUT")]
+    generator: Option<GeneratorKind>,
+
+    /// Load offered by the synthetic traffic as a fraction of the line rate
+    #[clap(long = "load", default_value = "0.1")]
+    load: f64,
+
+    /// Size of the synthetic packets in bytes, or imix
+    #[clap(long = "packet-size", default_value = "1000")]
+    packet_size: PacketSize,
+
+    /// Ratio between the peak and the mean rate of the onoff and mmpp generators
+    #[clap(long = "burstiness", default_value = "4")]
+    burstiness: f64,
+
+    /// Mean length of the bursts of the onoff and mmpp generators
+    #[clap(long = "burst-length", default_value = "100us")]
+    burst_length: Time,
+
+    /// Shape of the Pareto distributions of the onoff generator
+    #[clap(long = "pareto-shape", default_value = "1.5")]
+    pareto_shape: f64,
+
+    /// Length of the synthetic traffic, e.g. 10s
+    #[clap(long = "duration", default_value = "1s")]
+    duration: Time,
+
+    /// Seed of the synthetic traffic generator
+    #[clap(long = "seed", default_value = "0")]
+    seed: u64,
+
+    /// Traffic input file to use. Format "time (s) length (bytes)", or a pcap or pcapng capture.
+    /// Leave empty for STDIN
     #[clap(name = "INPUT")]
     input: Option<PathBuf>,
 
+    /// What to do with the lines of a text INPUT that cannot be parsed, and with the packets of
+    /// either INPUT whose arrival goes backwards
+    #[clap(long = "on-error", value_enum, default_value = "fail")]
+    on_error: ErrorAction,
+
+    /// Map a text INPUT file into memory instead of reading it
+    #[cfg(feature = "mmap")]
+    #[clap(long = "mmap")]
+    mmap: bool,
+
     /// Traffic output file. Same format as INPUT. Uses stdout if not present.
     #[clap(short = 'o', long = "output")]
     output: Option<PathBuf>,
 
+    /// Write the output trace as a pcap capture. Implied by a .pcap output file
+    #[clap(long = "output-pcap")]
+    output_pcap: bool,
+
+    /// Fill the frames of the output capture with dummy payloads
+    #[clap(long = "pcap-payload")]
+    pcap_payload: bool,
+
     /// Log output filename, if present
     #[clap(short = 'l', long = "log")]
     log: Option<PathBuf>,
 
+    /// Format of the output trace, the state log and the summary
+    #[clap(long = "format", value_enum, default_value = "text")]
+    format: FormatKind,
+
+    /// Packet delay histogram output filename, if present
+    #[clap(long = "delay-hist")]
+    delay_hist: Option<PathBuf>,
+
+    /// Report the delay percentiles. Keeps the delay of every packet in
+    /// memory
+    #[clap(long = "delay-percentiles")]
+    delay_percentiles: bool,
+
+    /// Queue length time series output filename, if present
+    #[clap(long = "queue-series")]
+    queue_series: Option<PathBuf>,
+
+    /// Queue occupancy distribution output filename, if present
+    #[clap(long = "queue-dist")]
+    queue_dist: Option<PathBuf>,
+
+    /// Delay breakdown output filename, if present. Splits the delay of every
+    /// packet by what it waited for
+    #[clap(long = "explain")]
+    explain: Option<PathBuf>,
+
+    /// Wake-up output filename, if present. Records the packet that caused
+    /// every wake-up and the packets transmitted until the next one
+    #[clap(long = "wakes")]
+    wakes: Option<PathBuf>,
+
+    /// Wake-ups by flow output filename, if present
+    #[clap(long = "wake-flows")]
+    wake_flows: Option<PathBuf>,
+
+    /// Width of the delay histogram bins
+    #[clap(long = "hist-bin", default_value = "1us", value_parser = positive_time)]
+    hist_bin: Time,
+
     /// Write verbose log. Includes every state change
     #[clap(short = 'v', long = "verbose")]
     verbose: bool,
+
+    /// Print help information
+    #[clap(long = "help", action = ArgAction::Help)]
+    help: Option<bool>,
+
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Simulate every combination of the given parameters and write a table with one row per run.
+    /// Parameters not swept take their value from the main options
+    Sweep(SweepOpt),
+    /// Report the idle gaps between transmissions of the input traffic on a link that never enters
+    /// LPI, and how much of them LPI could use
+    Analyze(AnalyzeOpt),
+}
+
+#[derive(Args, Debug)]
+#[clap(disable_help_flag = true)]
+struct AnalyzeOpt {
+    /// Report output filename. Uses stdout if not present
+    #[clap(short = 'o', long = "output")]
+    output: Option<PathBuf>,
+
+    /// Gap distribution output filename, if present. Counts the gaps in every decade of length
+    #[clap(long = "gap-dist")]
+    gap_dist: Option<PathBuf>,
+
+    /// Print help information
+    #[clap(long = "help", action = ArgAction::Help)]
+    help: Option<bool>,
+}
+
+#[derive(Args, Debug)]
+#[clap(disable_help_flag = true)]
+struct SweepOpt {
+    /// Hysteresis values. Comma separated values and start:end:step ranges, e.g. 0:10us:2us
+    #[clap(short = 'h', long = "hyst")]
+    hyst: Option<Values<Time>>,
+
+    /// Delay values. Comma separated values and start:end:step ranges, e.g. 0:10us:2us
+    #[clap(short = 'd', long = "delay")]
+    delay: Option<Values<Time>>,
+
+    /// Loads offered by the synthetic traffic. Comma separated values and start:end:step ranges
+    #[clap(long = "load")]
+    load: Option<Values>,
+
+    /// Comma separated PHY profiles
+    #[clap(long = "phy", value_delimiter = ',')]
+    phy: Vec<PhyProfile>,
+
+    /// Ts values. Comma separated values and start:end:step ranges, e.g. 0:10us:2us
+    #[clap(long = "ts")]
+    ts: Option<Values<Time>>,
+
+    /// Tw values. Comma separated values and start:end:step ranges, e.g. 0:10us:2us
+    #[clap(long = "tw")]
+    tw: Option<Values<Time>>,
+
+    /// Number of worker threads. Defaults to the number of CPUs
+    #[clap(long = "threads")]
+    threads: Option<usize>,
+
+    /// Write comma separated values, as with --format csv
+    #[clap(long = "csv")]
+    csv: bool,
+
+    /// Results table output filename. Uses stdout if not present
+    #[clap(short = 'o', long = "output")]
+    output: Option<PathBuf>,
+
+    /// Print help information
+    #[clap(long = "help", action = ArgAction::Help)]
+    help: Option<bool>,
 }
 
-struct PacketsFromRead<'a, R: BufRead + ?Sized> {
-    is: &'a mut R,
+fn positive_time(s: &str) -> Result<Time, String> {
+    match s.parse::<Time>() {
+        Ok(Time(0)) => Err("must be greater than zero".to_string()),
+        Ok(time) => Ok(time),
+        Err(err) => Err(err.to_string()),
+    }
 }
 
-impl<'a, R: BufRead + ?Sized> PacketsFromRead<'a, R> {
-    pub fn new(buf: &'a mut R) -> PacketsFromRead<'a, R> {
-        PacketsFromRead { is: buf }
+fn alpha(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(alpha) if alpha > 0.0 && alpha <= 1.0 => Ok(alpha),
+        Ok(_) => Err("must be greater than 0 and at most 1".to_string()),
+        Err(err) => Err(err.to_string()),
     }
 }
 
-impl<'a, R: BufRead + ?Sized> Iterator for PacketsFromRead<'a, R> {
-    type Item = Packet;
+impl Opt {
+    /// The selected PHY profile with the overrides applied.
+    fn phy_profile(&self, phy: PhyProfile) -> PhyProfile {
+        let mut phy = phy;
+        if let Some(ts) = self.ts {
+            phy = phy.with_ts(ts);
+        }
+        if let Some(tw) = self.tw {
+            phy = phy.with_tw(tw);
+        }
+        if let Some(capacity) = self.capacity {
+            phy = phy.with_capacity(capacity);
+        }
+        if let Some(power) = self.power_on {
+            phy = phy.with_power(power.watts(phy.power()), phy.lpi_power());
+        }
+
+        phy
+    }
 
-    fn next(&mut self) -> Option<Packet> {
-        let line = &mut String::new();
+    fn power_model(&self, phy: &PhyProfile) -> PowerModel {
+        let mut power_model = PowerModel::from_phy(phy);
+        for (status, power) in [
+            (Status::Off, self.power_off),
+            (Status::TOn, self.power_ton),
+            (Status::TOff, self.power_toff),
+            (Status::Refresh, self.power_refresh),
+        ] {
+            if let Some(power) = power {
+                power_model = power_model.with_power(status, power);
+            }
+        }
 
-        match self.is.read_line(line) {
-            Err(_) => None,
-            _ => {
-                let values: Vec<&str> = line.split_whitespace().collect();
+        power_model
+    }
 
-                match values.len() {
-                    0 => None, // Just an empty line
-                    2 => Some(Packet::new(
-                        Time::from_secs(values[0].parse().unwrap_or_else(|_| {
-                            panic!("{} is not a valid arrival time.", values[0])
-                        })),
-                        values[1]
-                            .parse()
-                            .unwrap_or_else(|_| panic!("{} is not a valid size.", values[1])),
-                    )),
-                    _ => {
-                        eprintln!("Malformed line \"{}\"", line);
-                        ::std::process::exit(1)
-                    }
+    fn check_generator(&self, load: f64) {
+        if load <= 0.0
+            || self.burstiness <= 1.0
+            || self.pareto_shape <= 1.0
+            || self.burst_length == Time(0)
+        {
+            eprintln!("The load must be positive, the burstiness and Pareto shape over one and bursts not empty.");
+            ::std::process::exit(1);
+        }
+    }
+
+    fn generator(
+        &self,
+        generator: GeneratorKind,
+        load: f64,
+        phy: &PhyProfile,
+    ) -> Box<dyn Iterator<Item = Packet>> {
+        let rate = generators::rate_for_load(load, phy.capacity(), self.packet_size);
+        let burst = self.burst_length;
+        let end = self.duration;
+        let generator: Box<dyn Iterator<Item = Packet>> = match generator {
+            GeneratorKind::Poisson => Box::new(Poisson::new(rate, self.packet_size, self.seed)),
+            GeneratorKind::Cbr => Box::new(Cbr::new(rate, self.packet_size, self.seed)),
+            GeneratorKind::Onoff => Box::new(OnOff::new(
+                rate,
+                rate * self.burstiness,
+                burst,
+                self.pareto_shape,
+                self.packet_size,
+                self.seed,
+            )),
+            GeneratorKind::Mmpp => {
+                // Equally long states whose rates keep the requested ratio and mean
+                let high = 2.0 * rate * self.burstiness / (self.burstiness + 1.0);
+                Box::new(Mmpp::new(
+                    [high, high / self.burstiness],
+                    [burst, burst],
+                    self.packet_size,
+                    self.seed,
+                ))
+            }
+        };
+
+        Box::new(generator.take_while(move |packet| packet.arrival() < end))
+    }
+
+    fn simulator<'a>(
+        &self,
+        hyst: Time,
+        delay: Time,
+        mut packets: Box<dyn Iterator<Item = Packet> + 'a>,
+        phy: PhyProfile,
+    ) -> simulator::Simulator<Box<dyn Iterator<Item = Packet> + 'a>> {
+        let mut policy: Box<dyn SleepPolicy> = match self.policy {
+            PolicyKind::Fixed => Box::new(TimerPolicy::new(hyst, delay)),
+            PolicyKind::Adaptive => Box::new(AdaptivePolicy::new(&phy, delay, self.alpha)),
+            PolicyKind::Deadline => Box::new(DeadlinePolicy::new(
+                &phy,
+                hyst,
+                self.deadline.expect("Required by clap"),
+            )),
+            PolicyKind::Oracle => {
+                // The whole input has to be read to know the future
+                let trace: Vec<Packet> = packets.collect();
+                let power_model = self.power_model(&phy);
+                let oracle =
+                    OraclePolicy::new(&phy, &power_model, trace.iter().map(Packet::arrival));
+                packets = Box::new(trace.into_iter());
+                Box::new(oracle)
+            }
+        };
+        if self.coalesce_packets.is_some() || self.coalesce_bytes.is_some() {
+            policy = Box::new(Coalescing::new(
+                policy,
+                self.coalesce_packets,
+                self.coalesce_bytes,
+            ));
+        }
+
+        simulator::Simulator::new_explicit(hyst, delay, packets, phy)
+            .with_buffer(BufferLimit::new(self.buffer_packets, self.buffer_bytes))
+            .with_refresh(self.refresh.into())
+            .with_policy(policy)
+    }
+}
+
+enum TraceWriter<W: Write> {
+    Records(RecordWriter<W>),
+    Pcap(PcapWriter<W>),
+}
+
+impl<W: Write> TraceWriter<W> {
+    fn write(&mut self, time: Time, packet: &Packet) -> io::Result<()> {
+        match self {
+            TraceWriter::Records(writer) => {
+                writer.write(&[("time", time.into()), ("size", packet.size().into())])
+            }
+            TraceWriter::Pcap(writer) => writer.write(time, packet),
+        }
+    }
+
+    fn flush(self) -> io::Result<()> {
+        match self {
+            TraceWriter::Records(writer) => writer.finish()?.flush(),
+            TraceWriter::Pcap(writer) => writer.into_inner()?.flush(),
+        }
+    }
+}
+
+/// Writes the state changes and the final summary. Every record of the CSV
+/// format has the same columns, so there they are written as a type, a
+/// time, and a field name and value.
+struct LogWriter<W: Write> {
+    records: RecordWriter<W>,
+}
+
+impl<W: Write> LogWriter<W> {
+    fn new(output: W, format: Format) -> LogWriter<W> {
+        LogWriter {
+            records: RecordWriter::new(output, format),
+        }
+    }
+
+    fn event(&mut self, kind: &str, time: Time, name: &str, value: Value) -> io::Result<()> {
+        match self.records.format() {
+            Format::Text => self.records.write(&[("time", time.into()), (name, value)]),
+            Format::Csv => self.records.write(&[
+                ("type", kind.into()),
+                ("time", time.into()),
+                ("name", name.into()),
+                ("value", value),
+            ]),
+            Format::Json | Format::Jsonl => {
+                self.records
+                    .write(&[("type", kind.into()), ("time", time.into()), (name, value)])
+            }
+        }
+    }
+
+    fn state(&mut self, time: Time, state: Status) -> io::Result<()> {
+        self.event("state", time, "state", state.to_string().into())
+    }
+
+    fn hysteresis(&mut self, time: Time, hyst: Time) -> io::Result<()> {
+        match self.records.format() {
+            Format::Text => self.event(
+                "hysteresis",
+                time,
+                "hysteresis",
+                format!("HYST={:e}", hyst.as_secs()).into(),
+            ),
+            _ => self.event("hysteresis", time, "hysteresis", hyst.into()),
+        }
+    }
+
+    fn summary(&mut self, summary: &[(&str, Value)]) -> io::Result<()> {
+        match self.records.format() {
+            Format::Text => unreachable!("The text summary is written by write_text_summary"),
+            Format::Csv => {
+                for (name, value) in summary {
+                    self.records.write(&[
+                        ("type", "summary".into()),
+                        ("time", Value::Null),
+                        ("name", (*name).into()),
+                        ("value", value.clone()),
+                    ])?;
                 }
+                Ok(())
+            }
+            Format::Json | Format::Jsonl => {
+                let mut record = vec![("type", "summary".into())];
+                record.extend(summary.iter().cloned());
+                self.records.write(&record)
             }
         }
     }
 }
 
-struct Stats {
-    last_state: Status,
-    totals: HashMap<Status, Time>,
-    total_time: Time,
+struct SweepJob {
+    phy: PhyProfile,
+    hyst: Time,
+    delay: Time,
+    load: Option<f64>,
 }
 
-impl Stats {
-    fn new() -> Stats {
-        Stats {
-            last_state: Status::Off,
-            totals: HashMap::new(),
-            total_time: Time(0),
+/// Times swept for a parameter, or `default` if not swept.
+fn sweep_times(values: &Option<Values<Time>>, default: Option<Time>) -> Vec<Option<Time>> {
+    match values {
+        Some(values) => values.iter().map(Some).collect(),
+        None => vec![default],
+    }
+}
+
+fn sweep_row(opt: &Opt, job: &SweepJob, trace: &[Packet]) -> Vec<(&'static str, Value)> {
+    let packets = match (opt.generator, job.load) {
+        (Some(generator), Some(load)) => opt.generator(generator, load, &job.phy),
+        _ => Box::new(trace.iter().copied()),
+    };
+    let power_model = opt.power_model(&job.phy);
+
+    let mut stats = StateStats::new();
+    let mut delays = if opt.delay_percentiles {
+        DelayStats::new().with_samples()
+    } else {
+        DelayStats::new()
+    };
+    let mut queue = QueueStats::new();
+    let mut dropped = 0u64;
+    for ev in opt.simulator(job.hyst, job.delay, packets, job.phy) {
+        match ev {
+            SimEvent::Departure { time, packet } => {
+                delays.record(time, &packet);
+                queue.record(time, &packet);
+            }
+            SimEvent::Drop { .. } => dropped += 1,
+            _ => {}
         }
+        stats.record(&ev);
     }
+    queue.finish(stats.span());
+
+    let total = stats.span();
+    let energy: f64 = stats
+        .times()
+        .map(|(state, time)| power_model.energy(state, time))
+        .sum();
+    // An empty trace spans no time, so it has no mean power nor savings
+    let power = (total > Time(0)).then(|| energy / total.as_secs());
+    let savings =
+        (total > Time(0)).then(|| 100.0 * (1.0 - energy / power_model.always_on_energy(total)));
 
-    fn update(&mut self, info: (Time, Status)) {
-        let (time, state) = info;
-        let stats = self.totals.entry(self.last_state).or_insert(Time(0));
-        self.last_state = state;
-        *stats = (*stats + time) - self.total_time;
-        self.total_time = time;
+    vec![
+        ("phy", job.phy.to_string().into()),
+        ("ts", job.phy.t_s().into()),
+        ("tw", job.phy.t_w().into()),
+        ("hyst", job.hyst.into()),
+        ("delay", job.delay.into()),
+        ("load", job.load.into()),
+        ("time_on", stats.time(Status::On).into()),
+        ("time_off", stats.time(Status::Off).into()),
+        ("time_t_on", stats.time(Status::TOn).into()),
+        ("time_t_off", stats.time(Status::TOff).into()),
+        ("time_refresh", stats.time(Status::Refresh).into()),
+        ("energy", energy.into()),
+        ("power", power.into()),
+        ("savings_pct", savings.into()),
+        ("dropped", dropped.into()),
+        ("delay_mean", delays.mean().into()),
+        ("delay_p50", delays.percentile(50.0).into()),
+        ("delay_p90", delays.percentile(90.0).into()),
+        ("delay_p99", delays.percentile(99.0).into()),
+        ("delay_p99_9", delays.percentile(99.9).into()),
+        ("delay_max", delays.max().into()),
+        ("queue_mean_packets", queue.mean_packets().into()),
+        ("queue_max_packets", (queue.max_packets() as u64).into()),
+    ]
+}
+
+fn run_sweep(opt: &Opt, sweep_opt: &SweepOpt, packets: impl Iterator<Item = Packet>) {
+    let loads: Vec<Option<f64>> = match (opt.generator, &sweep_opt.load) {
+        (Some(_), Some(loads)) => loads.iter().map(Some).collect(),
+        (Some(_), None) => vec![Some(opt.load)],
+        (None, Some(_)) => {
+            eprintln!("Sweeping the load needs a traffic generator.");
+            ::std::process::exit(1);
+        }
+        (None, None) => vec![None],
+    };
+    for load in loads.iter().flatten() {
+        opt.check_generator(*load);
     }
 
-    fn get_total_time(&self) -> Time {
-        self.total_time
+    // Every run replays the same trace, so read it only once
+    let trace: Vec<Packet> = match opt.generator {
+        Some(_) => Vec::new(),
+        None => packets.collect(),
+    };
+
+    let phys = match sweep_opt.phy.is_empty() {
+        true => vec![opt.phy],
+        false => sweep_opt.phy.clone(),
+    };
+    let mut jobs = Vec::new();
+    for phy in phys {
+        let phy = opt.phy_profile(phy);
+        for ts in sweep_times(&sweep_opt.ts, None) {
+            for tw in sweep_times(&sweep_opt.tw, None) {
+                let phy = ts.map_or(phy, |ts| phy.with_ts(ts));
+                let phy = tw.map_or(phy, |tw| phy.with_tw(tw));
+                for hyst in sweep_times(&sweep_opt.hyst, Some(opt.hyst)) {
+                    for delay in sweep_times(&sweep_opt.delay, Some(opt.delay)) {
+                        for load in &loads {
+                            jobs.push(SweepJob {
+                                phy,
+                                hyst: hyst.unwrap(),
+                                delay: delay.unwrap(),
+                                load: *load,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let threads = sweep_opt
+        .threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |n| n.get()));
+    let rows = sweep::run(&jobs, threads, |job| sweep_row(opt, job, &trace));
+
+    let table_writer = match &sweep_opt.output {
+        Some(filename) => {
+            let file = File::create(filename);
+            if file.is_err() {
+                eprintln!("Could not open results file for writing.");
+                ::std::process::exit(2);
+            }
+            BufWriter::new(Box::new(file.unwrap()) as Box<dyn Write>)
+        }
+        None => BufWriter::new(Box::new(io::stdout().lock()) as Box<dyn Write>),
+    };
+    let format = if sweep_opt.csv {
+        Format::Csv
+    } else {
+        opt.format.into()
+    };
+
+    write_table(RecordWriter::new(table_writer, format), &rows)
+        .expect("Error writing results table.");
+}
+
+/// Writes the records of a table, with a header in the text format too.
+fn write_table<W: Write>(
+    mut writer: RecordWriter<W>,
+    rows: &[Vec<(&str, Value)>],
+) -> io::Result<()> {
+    if let (Format::Text, Some(row)) = (writer.format(), rows.first()) {
+        let names: Vec<_> = row.iter().map(|(name, _)| *name).collect();
+        writeln!(writer.get_mut(), "{}", names.join("\t"))?;
     }
+    for row in rows {
+        writer.write(row)?;
+    }
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Everything collected from the events of a simulation.
+struct RunStats {
+    states: StateStats,
+    dropped: u64,
+    delays: DelayStats,
+    queue: QueueStats,
+    breakdowns: BreakdownStats,
+    wakes: WakeStats,
 }
 
-impl<'a> IntoIterator for &'a mut Stats {
-    type Item = (&'a Status, &'a Time);
-    type IntoIter = std::collections::hash_map::Iter<'a, Status, Time>;
+fn run_analyze(opt: &Opt, analyze_opt: &AnalyzeOpt, packets: impl Iterator<Item = Packet>) {
+    let mut gaps = GapStats::new(opt.phy_profile(opt.phy));
+    for packet in packets {
+        gaps.record(&packet);
+    }
+
+    let report_writer = match &analyze_opt.output {
+        Some(filename) => {
+            let file = File::create(filename);
+            if file.is_err() {
+                eprintln!("Could not open report file for writing.");
+                ::std::process::exit(2);
+            }
+            BufWriter::new(Box::new(file.unwrap()) as Box<dyn Write>)
+        }
+        None => BufWriter::new(Box::new(io::stdout().lock()) as Box<dyn Write>),
+    };
+    let over_break_even = gaps.over_break_even().count() as u64;
+    let report = [
+        ("packets", gaps.packets().into()),
+        ("time_total", gaps.span().into()),
+        ("time_busy", gaps.busy().into()),
+        ("time_idle", gaps.idle().into()),
+        ("gaps", (gaps.count() as u64).into()),
+        ("gap_mean", gaps.mean().into()),
+        ("gap_p50", gaps.percentile(50.0).into()),
+        ("gap_p90", gaps.percentile(90.0).into()),
+        ("gap_p99", gaps.percentile(99.0).into()),
+        ("gap_max", gaps.max().into()),
+        ("break_even", gaps.break_even().into()),
+        ("gaps_over_break_even", over_break_even.into()),
+        (
+            "idle_over_break_even_pct",
+            gaps.over_break_even_pct().into(),
+        ),
+        ("lpi_best_pct", gaps.best_lpi_pct().into()),
+    ];
+    write_report(RecordWriter::new(report_writer, opt.format.into()), &report)
+        .expect("Error writing report.");
+
+    if let Some(filename) = &analyze_opt.gap_dist {
+        let file = File::create(filename);
+        if file.is_err() {
+            eprintln!("Could not open gap distribution file for writing.");
+            ::std::process::exit(2);
+        }
+        let idle = gaps.idle();
+        let mut dist_writer = RecordWriter::new(BufWriter::new(file.unwrap()), opt.format.into());
+        for (from, count, time) in gaps.distribution() {
+            dist_writer
+                .write(&[
+                    ("from", from.into()),
+                    ("gaps", (count as u64).into()),
+                    ("time", time.into()),
+                    ("idle_pct", (100.0 * (time / idle)).into()),
+                ])
+                .expect("Error writing gap distribution.");
+        }
+        dist_writer
+            .finish()
+            .expect("Error writing gap distribution.");
+    }
+}
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.totals.iter()
+/// Writes a report as a single record in the JSON formats, and as a `name`
+/// and `value` row per field otherwise.
+fn write_report<W: Write>(mut writer: RecordWriter<W>, report: &[(&str, Value)]) -> io::Result<()> {
+    match writer.format() {
+        Format::Text | Format::Csv => {
+            for (name, value) in report {
+                writer.write(&[("name", (*name).into()), ("value", value.clone())])?;
+            }
+        }
+        Format::Json | Format::Jsonl => writer.write(report)?,
     }
+    writer.finish()?;
+
+    Ok(())
+}
+
+fn write_text_summary<W: Write>(
+    log_writer: &mut W,
+    run: &mut RunStats,
+    power_model: &PowerModel,
+) -> io::Result<()> {
+    let RunStats {
+        states: stats,
+        dropped,
+        delays,
+        queue,
+        breakdowns,
+        wakes,
+    } = run;
+    let dropped = *dropped;
+    let total = stats.span();
+    let mut energy = 0.0;
+    for (state, time) in stats.times() {
+        if time > Time(0) {
+            energy += power_model.energy(state, time);
+            writeln!(
+                log_writer,
+                "#\t{}:\t{:e}s\t{:5.2}%",
+                state,
+                time.as_secs(),
+                100.0 * (time / total)
+            )?;
+        }
+    }
+    writeln!(
+        log_writer,
+        "#\tLPI_ENTRIES:\t{}\n#\tWAKE_UPS:\t{}",
+        stats.lpi_entries(),
+        stats.wake_ups()
+    )?;
+    if let Some(packets) = wakes.mean_packets() {
+        writeln!(log_writer, "#\tWAKE_PACKETS:\t{:e}", packets)?;
+    }
+
+    let always_on = power_model.always_on_energy(total);
+    writeln!(log_writer, "#\tENERGY:\t{:e}J", energy)?;
+    // An empty trace spans no time, so it has no mean power nor savings
+    if total > Time(0) {
+        writeln!(
+            log_writer,
+            "#\tPOWER:\t{:e}W\n#\tSAVINGS:\t{:5.2}%",
+            energy / total.as_secs(),
+            100.0 * (1.0 - energy / always_on)
+        )?;
+    } else {
+        writeln!(log_writer, "#\tPOWER:\t-\n#\tSAVINGS:\t-")?;
+    }
+
+    writeln!(
+        log_writer,
+        "#\tDROPPED:\t{}\t{:5.2}%",
+        dropped,
+        100.0 * dropped as f64 / (dropped + delays.count() as u64).max(1) as f64
+    )?;
+
+    if let (Some(min), Some(mean), Some(max)) = (delays.min(), delays.mean(), delays.max()) {
+        writeln!(
+            log_writer,
+            "#\tDELAY_MIN:\t{:e}s\n#\tDELAY_MEAN:\t{:e}s\n#\tDELAY_MAX:\t{:e}s",
+            min.as_secs(),
+            mean,
+            max.as_secs()
+        )?;
+        for p in [50.0, 90.0, 99.0, 99.9] {
+            if let Some(percentile) = delays.percentile(p) {
+                writeln!(log_writer, "#\tDELAY_P{}:\t{:e}s", p, percentile.as_secs())?;
+            }
+        }
+    }
+
+    for (name, share) in breakdowns.shares().into_iter().flatten() {
+        writeln!(
+            log_writer,
+            "#\tDELAY_{}:\t{:5.2}%",
+            name.to_uppercase(),
+            share
+        )?;
+    }
+
+    if let (Some(packets), Some(bytes)) = (queue.mean_packets(), queue.mean_bytes()) {
+        writeln!(
+            log_writer,
+            "#\tQUEUE_MEAN:\t{:e}\t{:e}B\n#\tQUEUE_MAX:\t{}\t{}B",
+            packets,
+            bytes,
+            queue.max_packets(),
+            queue.max_bytes()
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Summary fields of the structured formats. Times are in s, energy in J,
+/// power in W and shares in percent.
+fn summary(run: &mut RunStats, power_model: &PowerModel) -> Vec<(&'static str, Value)> {
+    let RunStats {
+        states: stats,
+        dropped,
+        delays,
+        queue,
+        breakdowns,
+        wakes,
+    } = run;
+    let dropped = *dropped;
+    let total = stats.span();
+    let energy: f64 = stats
+        .times()
+        .map(|(state, time)| power_model.energy(state, time))
+        .sum();
+
+    let shares = breakdowns.shares();
+    let share = |i: usize| shares.map(|shares| shares[i].1);
+
+    vec![
+        ("time_total", total.into()),
+        ("time_on", stats.time(Status::On).into()),
+        ("time_off", stats.time(Status::Off).into()),
+        ("time_t_on", stats.time(Status::TOn).into()),
+        ("time_t_off", stats.time(Status::TOff).into()),
+        ("time_refresh", stats.time(Status::Refresh).into()),
+        ("lpi_entries", stats.lpi_entries().into()),
+        ("wake_ups", stats.wake_ups().into()),
+        ("wake_packets_mean", wakes.mean_packets().into()),
+        ("energy", energy.into()),
+        ("power", (energy / total.as_secs()).into()),
+        (
+            "savings_pct",
+            (100.0 * (1.0 - energy / power_model.always_on_energy(total))).into(),
+        ),
+        ("dropped", dropped.into()),
+        (
+            "dropped_pct",
+            (100.0 * dropped as f64 / (dropped + delays.count() as u64).max(1) as f64).into(),
+        ),
+        ("delay_min", delays.min().into()),
+        ("delay_mean", delays.mean().into()),
+        ("delay_max", delays.max().into()),
+        ("delay_p50", delays.percentile(50.0).into()),
+        ("delay_p90", delays.percentile(90.0).into()),
+        ("delay_p99", delays.percentile(99.0).into()),
+        ("delay_p99_9", delays.percentile(99.9).into()),
+        ("delay_sleep_pct", share(0).into()),
+        ("delay_idle_pct", share(1).into()),
+        ("delay_wake_pct", share(2).into()),
+        ("delay_queue_pct", share(3).into()),
+        ("delay_transmission_pct", share(4).into()),
+        ("queue_mean_packets", queue.mean_packets().into()),
+        ("queue_mean_bytes", queue.mean_bytes().into()),
+        ("queue_max_packets", (queue.max_packets() as u64).into()),
+        ("queue_max_bytes", queue.max_bytes().into()),
+    ]
+}
+
+/// Applies the `on_error` action to the errors of a text trace or capture.
+fn check_trace<'a>(
+    trace: impl Iterator<Item = Result<Packet, TraceError>> + 'a,
+    on_error: ErrorAction,
+) -> Box<dyn Iterator<Item = Packet> + 'a> {
+    Box::new(trace.filter_map(move |packet| match (packet, on_error) {
+        (Ok(packet), _) => Some(packet),
+        (Err(_), ErrorAction::Skip) => None,
+        (Err(err), ErrorAction::Warn) => {
+            eprintln!("Ignoring input {}.", err);
+            None
+        }
+        (Err(err), ErrorAction::Fail) => {
+            eprintln!("Error reading input {}.", err);
+            ::std::process::exit(1)
+        }
+    }))
 }
 
 fn main() {
@@ -121,14 +1038,32 @@ fn main() {
 
     let verbose = opt.verbose;
 
-    let hyst = Time(opt.hyst);
-    let maxidle = Time(opt.delay);
+    let hyst = opt.hyst;
+    let maxidle = opt.delay;
+
+    let phy = opt.phy_profile(opt.phy);
+    let power_model = opt.power_model(&phy);
 
     let stdin = io::stdin();
+    #[cfg(feature = "mmap")]
+    let mapped;
     let mut file_reader;
     let mut stdin_reader;
 
-    let input_read: &mut dyn BufRead = match opt.input {
+    // - stands for stdin
+    let input = opt
+        .input
+        .as_ref()
+        .filter(|filename| filename.as_os_str() != "-");
+    let capture_extension = input
+        .and_then(|filename| filename.extension())
+        .is_some_and(|ext| {
+            ["pcap", "pcapng", "cap"]
+                .iter()
+                .any(|e| ext.eq_ignore_ascii_case(e))
+        });
+
+    let input_read: &mut dyn BufRead = match input {
         Some(filename) => {
             let file = File::open(filename);
             if file.is_err() {
@@ -144,9 +1079,55 @@ fn main() {
         }
     };
 
+    let packets: Box<dyn Iterator<Item = Packet>> = if let Some(generator) = opt.generator {
+        opt.check_generator(opt.load);
+        opt.generator(generator, opt.load, &phy)
+    } else if capture_extension || pcap::is_capture(input_read.fill_buf().unwrap_or(&[])) {
+        match PcapReader::new(input_read) {
+            Ok(reader) => check_trace(reader, opt.on_error),
+            Err(err) => {
+                eprintln!("Could not read input capture: {}", err);
+                ::std::process::exit(1);
+            }
+        }
+    } else {
+        #[cfg(feature = "mmap")]
+        if let (true, Some(filename)) = (opt.mmap, input) {
+            // Safety: traces are not expected to change while simulated
+            mapped = match File::open(filename).and_then(|file| unsafe { trace::map(&file) }) {
+                Ok(mapped) => mapped,
+                Err(err) => {
+                    eprintln!("Could not map input file: {}", err);
+                    ::std::process::exit(1);
+                }
+            };
+            check_trace(TraceSliceReader::new(&mapped), opt.on_error)
+        } else {
+            check_trace(TraceReader::new(input_read), opt.on_error)
+        }
+        #[cfg(not(feature = "mmap"))]
+        check_trace(TraceReader::new(input_read), opt.on_error)
+    };
+
+    if let Some(Command::Sweep(sweep_opt)) = &opt.command {
+        run_sweep(&opt, sweep_opt, packets);
+        return;
+    }
+    if let Some(Command::Analyze(analyze_opt)) = &opt.command {
+        run_analyze(&opt, analyze_opt, packets);
+        return;
+    }
+
     let stdout = io::stdout();
 
-    let mut trace_writer = match opt.output {
+    let output_pcap = opt.output_pcap
+        || opt
+            .output
+            .as_ref()
+            .and_then(|filename| filename.extension())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("pcap"));
+
+    let trace_writer = match &opt.output {
         Some(filename) => {
             let file = File::create(filename);
             if file.is_err() {
@@ -157,63 +1138,227 @@ fn main() {
         }
         None => BufWriter::new(Box::new(stdout.lock()) as Box<dyn Write>),
     };
+    let mut trace_writer = if output_pcap {
+        match PcapWriter::new(trace_writer, opt.pcap_payload) {
+            Ok(writer) => TraceWriter::Pcap(writer),
+            Err(err) => {
+                eprintln!("Could not write output capture: {}", err);
+                ::std::process::exit(2);
+            }
+        }
+    } else {
+        TraceWriter::Records(RecordWriter::new(trace_writer, opt.format.into()))
+    };
 
     let mut log_writer;
-    match opt.log {
+    match &opt.log {
         Some(filename) => {
             let file = File::create(filename);
             if file.is_err() {
                 eprintln!("Could not open log file for writing.");
                 ::std::process::exit(2);
             }
-            log_writer = Some(BufWriter::new(file.unwrap()));
+            log_writer = Some(LogWriter::new(
+                BufWriter::new(file.unwrap()),
+                opt.format.into(),
+            ));
         }
         None => log_writer = None,
     }
 
-    let simul = simulator::Simulator::new(hyst, maxidle, PacketsFromRead::new(input_read));
+    let mut explain_writer = opt.explain.as_ref().map(|filename| {
+        let file = File::create(filename);
+        if file.is_err() {
+            eprintln!("Could not open delay breakdown file for writing.");
+            ::std::process::exit(2);
+        }
+        RecordWriter::new(BufWriter::new(file.unwrap()), opt.format.into())
+    });
+
+    let mut simul = opt.simulator(hyst, maxidle, packets, phy);
+
+    let mut run = RunStats {
+        states: StateStats::new(),
+        dropped: 0,
+        delays: if opt.delay_percentiles || opt.delay_hist.is_some() {
+            DelayStats::new().with_samples()
+        } else {
+            DelayStats::new()
+        },
+        queue: match opt.queue_series {
+            Some(_) => QueueStats::new().with_series(),
+            None => QueueStats::new(),
+        },
+        breakdowns: BreakdownStats::new(),
+        wakes: WakeStats::new(),
+    };
+    let mut hysteresis = None;
+    while let Some(ev) = simul.next() {
+        let breakdown = run.breakdowns.record(&ev);
 
-    let mut stats = Stats::new();
-    for state in simul
-        .map(|ev| match ev {
-            (time, Some(packet), _) => {
-                writeln!(trace_writer, "{:e}\t{}", time.as_secs(), packet.size())
+        match ev {
+            SimEvent::Departure { time, packet } => {
+                trace_writer
+                    .write(time, &packet)
                     .expect("Error writing output trace.");
-                ev
-            }
-            (_, None, _) => ev,
-        })
-        .filter_map(|ev| match ev {
-            (time, _, Some(state)) => Some((time, state)),
-            _ => None,
-        })
-        .map(|ev| {
-            if verbose && log_writer.is_some() {
-                writeln!(
-                    log_writer.as_mut().unwrap(),
-                    "{:e}\t{}",
-                    ev.0.as_secs(),
-                    ev.1
-                )
-                .expect("Error writing output log.");
-            }
-            ev
-        })
-    {
-        stats.update(state);
-    }
-
-    if log_writer.is_some() {
-        let total = stats.get_total_time();
-        for (state, time) in stats.into_iter() {
-            writeln!(
-                log_writer.as_mut().unwrap(),
-                "#\t{}:\t{:e}s\t{:5.2}%",
-                state,
-                time.as_secs(),
-                100.0 * (*time / total)
-            )
+                run.delays.record(time, &packet);
+                run.queue.record(time, &packet);
+
+                if let (Some(explain_writer), Some(breakdown)) =
+                    (explain_writer.as_mut(), breakdown)
+                {
+                    let mut record = vec![
+                        ("time", time.into()),
+                        ("arrival", packet.arrival().into()),
+                        ("size", packet.size().into()),
+                        ("delay", (time - packet.arrival()).into()),
+                    ];
+                    record.extend(breakdown.parts().map(|(name, part)| (name, part.into())));
+                    explain_writer
+                        .write(&record)
+                        .expect("Error writing delay breakdown.");
+                }
+            }
+            SimEvent::StateChange { time, to, .. } => {
+                if let Some(log_writer) = log_writer.as_mut().filter(|_| verbose) {
+                    log_writer
+                        .state(time, to)
+                        .expect("Error writing output log.");
+                }
+            }
+            SimEvent::Drop { .. } => run.dropped += 1,
+            SimEvent::Arrival { .. } => {}
+        }
+        run.states.record(&ev);
+        run.wakes.record(&ev);
+
+        // Adaptive policies change their hysteresis as they observe the traffic
+        if simul.policy().hysteresis() != hysteresis {
+            hysteresis = simul.policy().hysteresis();
+            if let (Some(log_writer), Some(hyst)) =
+                (log_writer.as_mut().filter(|_| verbose), hysteresis)
+            {
+                log_writer
+                    .hysteresis(ev.time(), hyst)
+                    .expect("Error writing output log.");
+            }
+        }
+    }
+
+    trace_writer.flush().expect("Error writing output trace.");
+    if let Some(explain_writer) = explain_writer {
+        explain_writer
+            .finish()
+            .expect("Error writing delay breakdown.");
+    }
+    run.queue.finish(run.states.span());
+
+    if let Some(mut log_writer) = log_writer {
+        if matches!(opt.format, FormatKind::Text) {
+            write_text_summary(log_writer.records.get_mut(), &mut run, &power_model)
+                .expect("Error writing to output log.");
+        } else {
+            log_writer
+                .summary(&summary(&mut run, &power_model))
+                .expect("Error writing to output log.");
+        }
+        log_writer
+            .records
+            .finish()
             .expect("Error writing to output log.");
+    }
+
+    if let Some(filename) = &opt.delay_hist {
+        let file = File::create(filename);
+        if file.is_err() {
+            eprintln!("Could not open histogram file for writing.");
+            ::std::process::exit(2);
+        }
+        let mut hist_writer = BufWriter::new(file.unwrap());
+        for (start, count) in run.delays.histogram(opt.hist_bin) {
+            writeln!(hist_writer, "{:e}\t{}", start.as_secs(), count)
+                .expect("Error writing delay histogram.");
+        }
+    }
+
+    if let Some(filename) = &opt.queue_series {
+        let file = File::create(filename);
+        if file.is_err() {
+            eprintln!("Could not open queue series file for writing.");
+            ::std::process::exit(2);
+        }
+        let mut series_writer = RecordWriter::new(BufWriter::new(file.unwrap()), opt.format.into());
+        for sample in run.queue.series().unwrap_or_default() {
+            series_writer
+                .write(&[
+                    ("time", sample.time.into()),
+                    ("packets", (sample.packets as u64).into()),
+                    ("bytes", sample.bytes.into()),
+                ])
+                .expect("Error writing queue series.");
+        }
+        series_writer.finish().expect("Error writing queue series.");
+    }
+
+    if let Some(filename) = &opt.queue_dist {
+        let file = File::create(filename);
+        if file.is_err() {
+            eprintln!("Could not open queue distribution file for writing.");
+            ::std::process::exit(2);
+        }
+        let mut dist_writer = RecordWriter::new(BufWriter::new(file.unwrap()), opt.format.into());
+        for (packets, share) in run.queue.distribution() {
+            dist_writer
+                .write(&[
+                    ("packets", (packets as u64).into()),
+                    ("share", share.into()),
+                ])
+                .expect("Error writing queue distribution.");
+        }
+        dist_writer
+            .finish()
+            .expect("Error writing queue distribution.");
+    }
+
+    if let Some(filename) = &opt.wakes {
+        let file = File::create(filename);
+        if file.is_err() {
+            eprintln!("Could not open wake-up file for writing.");
+            ::std::process::exit(2);
+        }
+        let mut wake_writer = RecordWriter::new(BufWriter::new(file.unwrap()), opt.format.into());
+        for wake in run.wakes.wakes() {
+            wake_writer
+                .write(&[
+                    ("time", wake.time.into()),
+                    ("cause_arrival", wake.cause.arrival().into()),
+                    ("cause_size", wake.cause.size().into()),
+                    ("cause_flow", wake.cause.flow().into()),
+                    ("packets", wake.packets.into()),
+                ])
+                .expect("Error writing wake-ups.");
+        }
+        wake_writer.finish().expect("Error writing wake-ups.");
+    }
+
+    if let Some(filename) = &opt.wake_flows {
+        let file = File::create(filename);
+        if file.is_err() {
+            eprintln!("Could not open wake-ups by flow file for writing.");
+            ::std::process::exit(2);
+        }
+        let mut flow_writer = RecordWriter::new(BufWriter::new(file.unwrap()), opt.format.into());
+        for flow in run.wakes.by_flow() {
+            flow_writer
+                .write(&[
+                    ("flow", flow.flow.into()),
+                    ("wakes", flow.wakes.into()),
+                    ("packets", flow.packets.into()),
+                ])
+                .expect("Error writing wake-ups by flow.");
         }
+        flow_writer
+            .finish()
+            .expect("Error writing wake-ups by flow.");
     }
 }
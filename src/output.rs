@@ -0,0 +1,188 @@
+use crate::simulator::Time;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// Format of the records written by a `RecordWriter`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Tab separated values with no header.
+    Text,
+    /// Comma separated values with a header taken from the first record.
+    Csv,
+    /// A single JSON array of objects.
+    Json,
+    /// A JSON object per line.
+    Jsonl,
+}
+
+/// A field value.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Int(u64),
+    Float(f64),
+    Str(String),
+    Null,
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Value {
+        Value::Int(value)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Value {
+        Value::Int(u64::from(value))
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Value {
+        Value::Float(value)
+    }
+}
+
+/// Times are written in seconds.
+impl From<Time> for Value {
+    fn from(value: Time) -> Value {
+        Value::Float(value.as_secs())
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Value {
+        Value::Str(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Value {
+        Value::Str(value)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Value {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl Value {
+    fn text(&self) -> String {
+        match self {
+            Value::Int(value) => value.to_string(),
+            Value::Float(value) => format!("{:e}", value),
+            Value::Str(value) => value.clone(),
+            Value::Null => String::new(),
+        }
+    }
+
+    fn csv(&self) -> String {
+        match self {
+            Value::Str(value) if value.contains([',', '"', '\n']) => {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            }
+            _ => self.text(),
+        }
+    }
+
+    fn json(&self) -> String {
+        match self {
+            Value::Float(value) if !value.is_finite() => "null".to_string(),
+            Value::Str(value) => json_string(value),
+            Value::Null => "null".to_string(),
+            _ => self.text(),
+        }
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::with_capacity(s.len() + 2);
+
+    json.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => write!(json, "\\u{:04x}", c as u32).unwrap(),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+
+    json
+}
+
+/// Writes records, lists of named fields, in one of the supported formats.
+/// CSV files take their header from the first record, so every record
+/// written to them must have the same fields.
+pub struct RecordWriter<W: Write> {
+    output: W,
+    format: Format,
+    first: bool,
+}
+
+impl<W: Write> RecordWriter<W> {
+    pub fn new(output: W, format: Format) -> RecordWriter<W> {
+        RecordWriter {
+            output,
+            format,
+            first: true,
+        }
+    }
+
+    pub fn format(&self) -> Format {
+        self.format
+    }
+
+    /// The underlying writer, for output that is not made of records.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.output
+    }
+
+    pub fn write(&mut self, record: &[(&str, Value)]) -> io::Result<()> {
+        match self.format {
+            Format::Text => {
+                let values: Vec<_> = record.iter().map(|(_, value)| value.text()).collect();
+                writeln!(self.output, "{}", values.join("\t"))?;
+            }
+            Format::Csv => {
+                if self.first {
+                    let names: Vec<_> = record.iter().map(|(name, _)| *name).collect();
+                    writeln!(self.output, "{}", names.join(","))?;
+                }
+                let values: Vec<_> = record.iter().map(|(_, value)| value.csv()).collect();
+                writeln!(self.output, "{}", values.join(","))?;
+            }
+            Format::Json => {
+                write!(self.output, "{}", if self.first { "[\n" } else { ",\n" })?;
+                write!(self.output, "{}", json_object(record))?;
+            }
+            Format::Jsonl => writeln!(self.output, "{}", json_object(record))?,
+        }
+        self.first = false;
+
+        Ok(())
+    }
+
+    /// Closes the JSON array, if any, and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        if self.format == Format::Json {
+            writeln!(self.output, "{}", if self.first { "[]" } else { "\n]" })?;
+        }
+        self.output.flush()?;
+
+        Ok(self.output)
+    }
+}
+
+fn json_object(record: &[(&str, Value)]) -> String {
+    let fields: Vec<_> = record
+        .iter()
+        .map(|(name, value)| format!("{}:{}", json_string(name), value.json()))
+        .collect();
+
+    format!("{{{}}}", fields.join(","))
+}
@@ -0,0 +1,497 @@
+use crate::simulator::Time;
+use crate::switch::Packet;
+use crate::trace::TraceError;
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read, Write};
+
+const PCAP_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_NANOS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SHB: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER: u32 = 0x1a2b_3c4d;
+
+const PCAPNG_IDB: u32 = 0x0000_0001;
+const PCAPNG_OPB: u32 = 0x0000_0002;
+const PCAPNG_EPB: u32 = 0x0000_0006;
+const IF_TSRESOL: u16 = 9;
+
+const SNAPLEN: u32 = 65535;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u16 = 101;
+const LINKTYPE_IPV4: u16 = 228;
+const LINKTYPE_IPV6: u16 = 229;
+
+/// Bytes of every frame read to find its flow. Enough for the Ethernet,
+/// VLAN, IPv6 and transport headers.
+const FLOW_HEADERS: usize = 64;
+
+/// Longest pcapng interface description block read. Its options are a few
+/// short strings and numbers.
+const MAX_INTERFACE_BLOCK: u32 = 1 << 16;
+
+/// Whether `magic`, the first bytes of a file, belong to a pcap or pcapng
+/// capture.
+pub fn is_capture(magic: &[u8]) -> bool {
+    if magic.len() < 4 {
+        return false;
+    }
+    let magic = [magic[0], magic[1], magic[2], magic[3]];
+
+    [PCAP_MICROS, PCAP_NANOS, PCAPNG_SHB]
+        .iter()
+        .any(|m| u32::from_le_bytes(magic) == *m || u32::from_be_bytes(magic) == *m)
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg.to_string())
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Pcap,
+    PcapNg,
+}
+
+/// Addresses, protocol and ports of an IP packet.
+#[derive(PartialEq, Eq, Hash)]
+struct FlowKey {
+    src: [u8; 16],
+    dst: [u8; 16],
+    protocol: u8,
+    ports: [u8; 4],
+}
+
+impl FlowKey {
+    /// Finds the flow of an IP packet, if it is one.
+    fn from_ip(ip: &[u8]) -> Option<FlowKey> {
+        let mut key = FlowKey {
+            src: [0; 16],
+            dst: [0; 16],
+            protocol: 0,
+            ports: [0; 4],
+        };
+        let transport = match ip.first()? >> 4 {
+            4 if ip.len() >= 20 => {
+                key.src[..4].copy_from_slice(&ip[12..16]);
+                key.dst[..4].copy_from_slice(&ip[16..20]);
+                key.protocol = ip[9];
+                // Only the first fragment has the transport header
+                let fragment = u16::from_be_bytes([ip[6], ip[7]]) & 0x1fff;
+                (fragment == 0).then(|| ip.get(usize::from(ip[0] & 0x0f) * 4..))
+            }
+            6 if ip.len() >= 40 => {
+                key.src.copy_from_slice(&ip[8..24]);
+                key.dst.copy_from_slice(&ip[24..40]);
+                key.protocol = ip[6];
+                Some(ip.get(40..))
+            }
+            _ => return None,
+        };
+
+        // TCP, UDP, DCCP, SCTP and UDP-Lite start with the ports
+        if let (Some(Some(transport)), 6 | 17 | 33 | 132 | 136) = (transport, key.protocol) {
+            if let Some(ports) = transport.get(..4) {
+                key.ports.copy_from_slice(ports);
+            }
+        }
+
+        Some(key)
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Interface {
+    /// Length of a timestamp unit in ns, as a fraction
+    resolution: (u64, u64),
+    linktype: u16,
+}
+
+/// Reads the packets of a pcap or pcapng capture. Packet sizes are the
+/// original lengths on the wire and arrival times are measured from the
+/// first packet in the capture. IP packets get the flow of their addresses,
+/// protocol and ports, numbered from zero in order of appearance.
+///
+/// Like `TraceReader`, packets whose timestamp goes backwards are reported
+/// and do not move the reference for the next ones, and I/O errors, which
+/// include malformed captures, end the packets.
+pub struct PcapReader<R: Read> {
+    input: R,
+    format: Format,
+    big_endian: bool,
+    interfaces: Vec<Interface>,
+    first: Option<u128>,
+    flows: HashMap<FlowKey, u32>,
+    /// Packets read so far
+    count: usize,
+    /// Timestamp of the last packet read, in ns
+    timestamp: u128,
+    /// Timestamp of the last packet in order, in ns
+    last: Option<u128>,
+    done: bool,
+}
+
+impl<R: Read> PcapReader<R> {
+    /// Reads the file header and prepares to read the packets.
+    pub fn new(mut input: R) -> io::Result<PcapReader<R>> {
+        let mut magic = [0u8; 4];
+        input.read_exact(&mut magic)?;
+
+        let mut reader = PcapReader {
+            input,
+            format: Format::Pcap,
+            big_endian: false,
+            interfaces: Vec::new(),
+            first: None,
+            flows: HashMap::new(),
+            count: 0,
+            timestamp: 0,
+            last: None,
+            done: false,
+        };
+
+        match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+            (PCAP_MICROS, _) | (_, PCAP_MICROS) => {
+                reader.big_endian = u32::from_be_bytes(magic) == PCAP_MICROS;
+                reader.file_header((1000, 1))?;
+            }
+            (PCAP_NANOS, _) | (_, PCAP_NANOS) => {
+                reader.big_endian = u32::from_be_bytes(magic) == PCAP_NANOS;
+                reader.file_header((1, 1))?;
+            }
+            (PCAPNG_SHB, _) => {
+                reader.format = Format::PcapNg;
+                reader.section_header()?;
+            }
+            _ => return Err(invalid("Not a pcap or pcapng file")),
+        }
+
+        Ok(reader)
+    }
+
+    /// Reads the remainder of a pcap file header, after its magic.
+    fn file_header(&mut self, resolution: (u64, u64)) -> io::Result<()> {
+        let header = self.bytes(20)?;
+        self.interfaces.push(Interface {
+            resolution,
+            linktype: self.u32(&header[16..20]) as u16,
+        });
+
+        Ok(())
+    }
+
+    fn discard(&mut self, len: u64) -> io::Result<()> {
+        let skipped = io::copy(&mut (&mut self.input).take(len), &mut io::sink())?;
+
+        if skipped < len {
+            Err(ErrorKind::UnexpectedEof.into())
+        } else {
+            Ok(())
+        }
+    }
+
+    fn bytes(&mut self, len: usize) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0; len];
+        self.input.read_exact(&mut buf)?;
+
+        Ok(buf)
+    }
+
+    fn u16(&self, bytes: &[u8]) -> u16 {
+        let bytes = [bytes[0], bytes[1]];
+        if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        }
+    }
+
+    fn u32(&self, bytes: &[u8]) -> u32 {
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        }
+    }
+
+    /// Reads the remainder of a pcapng section header block, after its type.
+    fn section_header(&mut self) -> io::Result<()> {
+        let head = self.bytes(8)?;
+        let order = [head[4], head[5], head[6], head[7]];
+
+        self.big_endian = match (u32::from_le_bytes(order), u32::from_be_bytes(order)) {
+            (PCAPNG_BYTE_ORDER, _) => false,
+            (_, PCAPNG_BYTE_ORDER) => true,
+            _ => return Err(invalid("Invalid pcapng byte-order magic")),
+        };
+        // Interface ids are local to every section
+        self.interfaces.clear();
+
+        let len = self.u32(&head[..4]);
+        if len < 28 {
+            return Err(invalid("Truncated pcapng section header block"));
+        }
+        self.discard(u64::from(len) - 12)
+    }
+
+    /// Timestamp resolution of an interface description block body.
+    fn resolution(&self, body: &[u8]) -> (u64, u64) {
+        let mut options = body.get(8..).unwrap_or(&[]);
+
+        while options.len() >= 4 {
+            let code = self.u16(&options[..2]);
+            let len = usize::from(self.u16(&options[2..4]));
+            let value = options.get(4..4 + len).unwrap_or(&[]);
+
+            if code == IF_TSRESOL && !value.is_empty() {
+                let exp = u32::from(value[0] & 0x7f);
+                return if value[0] & 0x80 == 0 {
+                    match exp {
+                        0..=9 => (10u64.pow(9 - exp), 1),
+                        _ => (1, 10u64.pow((exp - 9).min(19))),
+                    }
+                } else {
+                    (1_000_000_000, 1u64 << exp.min(63))
+                };
+            }
+            if code == 0 {
+                break;
+            }
+            options = options.get(4 + len.div_ceil(4) * 4..).unwrap_or(&[]);
+        }
+
+        (1000, 1)
+    }
+
+    /// Flow of a frame from its first bytes.
+    fn flow(&mut self, linktype: u16, frame: &[u8]) -> Option<u32> {
+        let ip = match linktype {
+            LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => frame,
+            _ if u32::from(linktype) == LINKTYPE_ETHERNET => {
+                let mut ethertype = frame.get(12..14)?;
+                let mut offset = 14;
+                // Skip VLAN tags
+                while ethertype == [0x81, 0x00] || ethertype == [0x88, 0xa8] {
+                    ethertype = frame.get(offset + 2..offset + 4)?;
+                    offset += 4;
+                }
+                match ethertype {
+                    [0x08, 0x00] | [0x86, 0xdd] => frame.get(offset..)?,
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        };
+
+        let key = FlowKey::from_ip(ip)?;
+        let next = self.flows.len() as u32;
+
+        Some(*self.flows.entry(key).or_insert(next))
+    }
+
+    fn packet(
+        &mut self,
+        interface: usize,
+        timestamp: u64,
+        size: u32,
+        frame: &[u8],
+    ) -> io::Result<Packet> {
+        let Interface {
+            resolution: (num, den),
+            linktype,
+        } = *self
+            .interfaces
+            .get(interface)
+            .ok_or_else(|| invalid("Packet from an undeclared interface"))?;
+        let ns = u128::from(timestamp) * u128::from(num) / u128::from(den);
+        let first = *self.first.get_or_insert(ns);
+        self.timestamp = ns;
+
+        // Packets before the first one go backwards and are never used
+        let packet = Packet::new(Time(ns.saturating_sub(first) as u64), size);
+        Ok(match self.flow(linktype, frame) {
+            Some(flow) => packet.with_flow(flow),
+            None => packet,
+        })
+    }
+
+    fn next_pcap(&mut self) -> io::Result<Option<Packet>> {
+        let mut header = [0u8; 16];
+        match self.input.read(&mut header[..1])? {
+            0 => return Ok(None),
+            _ => self.input.read_exact(&mut header[1..])?,
+        }
+
+        let (unit, _) = self.interfaces[0].resolution;
+        let timestamp = u64::from(self.u32(&header[..4])) * (1_000_000_000 / unit)
+            + u64::from(self.u32(&header[4..8]));
+        let caplen = self.u32(&header[8..12]);
+        let frame = self.bytes(FLOW_HEADERS.min(caplen as usize))?;
+        self.discard(u64::from(caplen) - frame.len() as u64)?;
+
+        self.packet(0, timestamp, self.u32(&header[12..16]), &frame)
+            .map(Some)
+    }
+
+    fn next_pcapng(&mut self) -> io::Result<Option<Packet>> {
+        loop {
+            let mut kind = [0u8; 4];
+            match self.input.read(&mut kind[..1])? {
+                0 => return Ok(None),
+                _ => self.input.read_exact(&mut kind[1..])?,
+            }
+
+            if u32::from_le_bytes(kind) == PCAPNG_SHB {
+                self.section_header()?;
+                continue;
+            }
+
+            let kind = self.u32(&kind);
+            let len = self.bytes(4)?;
+            let len = self.u32(&len);
+            if len < 12 || !len.is_multiple_of(4) {
+                return Err(invalid("Invalid pcapng block length"));
+            }
+            // Only the head of packet blocks is needed, and the length read
+            // from the file is no allocation size to trust
+            let body_len = len as usize - 12;
+            let keep = match kind {
+                PCAPNG_IDB if len > MAX_INTERFACE_BLOCK => {
+                    return Err(invalid("Too long pcapng interface description block"))
+                }
+                PCAPNG_IDB => body_len,
+                PCAPNG_EPB | PCAPNG_OPB => body_len.min(20 + FLOW_HEADERS),
+                _ => 0,
+            };
+            let body = self.bytes(keep)?;
+            self.discard((body_len - keep + 4) as u64)?;
+
+            match kind {
+                PCAPNG_IDB => {
+                    let interface = Interface {
+                        resolution: self.resolution(&body),
+                        linktype: body.get(..2).map_or(0, |linktype| self.u16(linktype)),
+                    };
+                    self.interfaces.push(interface);
+                }
+                PCAPNG_EPB if body.len() >= 20 => {
+                    let interface = self.u32(&body[..4]) as usize;
+                    let timestamp =
+                        u64::from(self.u32(&body[4..8])) << 32 | u64::from(self.u32(&body[8..12]));
+                    let frame = captured(&body, self.u32(&body[12..16]));
+                    return self
+                        .packet(interface, timestamp, self.u32(&body[16..20]), frame)
+                        .map(Some);
+                }
+                PCAPNG_OPB if body.len() >= 20 => {
+                    let interface = usize::from(self.u16(&body[..2]));
+                    let timestamp =
+                        u64::from(self.u32(&body[4..8])) << 32 | u64::from(self.u32(&body[8..12]));
+                    let frame = captured(&body, self.u32(&body[12..16]));
+                    return self
+                        .packet(interface, timestamp, self.u32(&body[16..20]), frame)
+                        .map(Some);
+                }
+                PCAPNG_EPB | PCAPNG_OPB => return Err(invalid("Truncated pcapng packet block")),
+                // Simple packet blocks carry no timestamp, the rest no packets
+                _ => continue,
+            }
+        }
+    }
+}
+
+/// Captured bytes of a pcapng packet block body.
+fn captured(body: &[u8], caplen: u32) -> &[u8] {
+    let end = body.len().min(20 + caplen as usize);
+    &body[20..end]
+}
+
+impl<R: Read> Iterator for PcapReader<R> {
+    type Item = Result<Packet, TraceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        self.count += 1;
+
+        let res = match self.format {
+            Format::Pcap => self.next_pcap(),
+            Format::PcapNg => self.next_pcapng(),
+        };
+
+        match res {
+            Ok(Some(packet)) => match self.last {
+                Some(last) if self.timestamp < last => Some(Err(TraceError::Backwards {
+                    line: self.count,
+                    arrival: packet.arrival(),
+                    previous: Time((last - self.first.unwrap()) as u64),
+                })),
+                _ => {
+                    self.last = Some(self.timestamp);
+                    Some(Ok(packet))
+                }
+            },
+            Ok(None) => None,
+            Err(source) => {
+                self.done = true;
+                Some(Err(TraceError::Io {
+                    line: self.count,
+                    source,
+                }))
+            }
+        }
+    }
+}
+
+/// Writes packets as a classic pcap capture with nanosecond timestamps.
+/// Only the original length of every frame is known, so frames are either
+/// captured with no bytes at all or with a zero-filled dummy payload.
+pub struct PcapWriter<W: Write> {
+    output: W,
+    payload: bool,
+}
+
+impl<W: Write> PcapWriter<W> {
+    /// Writes the file header. Frames get a dummy payload, up to the
+    /// capture snapshot length, if `payload` is set.
+    pub fn new(mut output: W, payload: bool) -> io::Result<PcapWriter<W>> {
+        output.write_all(&PCAP_NANOS.to_le_bytes())?;
+        output.write_all(&2u16.to_le_bytes())?;
+        output.write_all(&4u16.to_le_bytes())?;
+        // Timezone offset and timestamp accuracy, always zero
+        output.write_all(&[0; 8])?;
+        output.write_all(&SNAPLEN.to_le_bytes())?;
+        output.write_all(&LINKTYPE_ETHERNET.to_le_bytes())?;
+
+        Ok(PcapWriter { output, payload })
+    }
+
+    /// Writes `packet` with `time` as its timestamp.
+    pub fn write(&mut self, time: Time, packet: &Packet) -> io::Result<()> {
+        let captured = if self.payload {
+            packet.size().min(SNAPLEN)
+        } else {
+            0
+        };
+
+        self.output
+            .write_all(&((time.0 / 1_000_000_000) as u32).to_le_bytes())?;
+        self.output
+            .write_all(&((time.0 % 1_000_000_000) as u32).to_le_bytes())?;
+        self.output.write_all(&captured.to_le_bytes())?;
+        self.output.write_all(&packet.size().to_le_bytes())?;
+        io::copy(
+            &mut io::repeat(0).take(u64::from(captured)),
+            &mut self.output,
+        )?;
+
+        Ok(())
+    }
+
+    /// Flushes and returns the underlying writer.
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.output.flush()?;
+
+        Ok(self.output)
+    }
+}
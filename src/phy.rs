@@ -0,0 +1,234 @@
+use crate::simulator::Time;
+use std::fmt::{Display, Error, Formatter};
+use std::str::FromStr;
+
+/// Timing parameters and line rate of an EEE capable PHY.
+///
+/// `t_s` is the time needed to enter LPI (sleep), `t_w` the time needed to
+/// leave it (wake), `t_q` the length of each quiet period while in LPI and
+/// `t_r` the length of the refresh periods between them. `power` and
+/// `lpi_power` are the power draw, in W, when active and when in LPI.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PhyProfile {
+    name: &'static str,
+    t_s: Time,
+    t_w: Time,
+    t_q: Time,
+    t_r: Time,
+    capacity: f64,
+    power: f64,
+    lpi_power: f64,
+}
+
+/// Known PHY profiles. Timings are the minimum values from IEEE 802.3az
+/// Table 78-4 and their IEEE 802.3bj/bz counterparts. Power figures are
+/// typical per-port values for commercial PHYs, with LPI drawing 10% of the
+/// active power.
+pub const PROFILES: [PhyProfile; 8] = [
+    PhyProfile {
+        name: "100base-tx",
+        t_s: Time(200_000),
+        t_w: Time(30_000),
+        t_q: Time(20_000_000),
+        t_r: Time(200_000),
+        capacity: 100e6,
+        power: 0.2,
+        lpi_power: 0.02,
+    },
+    PhyProfile {
+        name: "1000base-t",
+        t_s: Time(182_000),
+        t_w: Time(16_500),
+        t_q: Time(20_000_000),
+        t_r: Time(198_000),
+        capacity: 1e9,
+        power: 0.6,
+        lpi_power: 0.06,
+    },
+    PhyProfile {
+        name: "2.5gbase-t",
+        t_s: Time(11_520),
+        t_w: Time(17_920),
+        t_q: Time(158_720),
+        t_r: Time(5_120),
+        capacity: 2.5e9,
+        power: 1.5,
+        lpi_power: 0.15,
+    },
+    PhyProfile {
+        name: "5gbase-t",
+        t_s: Time(5_760),
+        t_w: Time(8_960),
+        t_q: Time(79_360),
+        t_r: Time(2_560),
+        capacity: 5e9,
+        power: 2.5,
+        lpi_power: 0.25,
+    },
+    PhyProfile {
+        name: "10gbase-t",
+        t_s: Time(2_880),
+        t_w: Time(4_480),
+        t_q: Time(39_680),
+        t_r: Time(1_280),
+        capacity: 10e9,
+        power: 4.0,
+        lpi_power: 0.4,
+    },
+    PhyProfile {
+        name: "10gbase-kr",
+        t_s: Time(1_700),
+        t_w: Time(11_250),
+        t_q: Time(1_700_000),
+        t_r: Time(16_000),
+        capacity: 10e9,
+        power: 0.5,
+        lpi_power: 0.05,
+    },
+    PhyProfile {
+        name: "40gbase-kr4",
+        t_s: Time(900),
+        t_w: Time(5_500),
+        t_q: Time(1_700_000),
+        t_r: Time(16_000),
+        capacity: 40e9,
+        power: 1.5,
+        lpi_power: 0.15,
+    },
+    PhyProfile {
+        name: "100gbase-kr4",
+        t_s: Time(900),
+        t_w: Time(5_500),
+        t_q: Time(1_700_000),
+        t_r: Time(16_000),
+        capacity: 100e9,
+        power: 3.0,
+        lpi_power: 0.3,
+    },
+];
+
+impl PhyProfile {
+    /// Creates a custom profile. Power figures are normalized to 1 W when
+    /// active and 0.1 W in LPI unless changed with `with_power`.
+    pub const fn new(
+        name: &'static str,
+        t_s: Time,
+        t_w: Time,
+        t_q: Time,
+        t_r: Time,
+        capacity: f64,
+    ) -> PhyProfile {
+        PhyProfile {
+            name,
+            t_s,
+            t_w,
+            t_q,
+            t_r,
+            capacity,
+            power: 1.0,
+            lpi_power: 0.1,
+        }
+    }
+
+    /// Looks up a profile by name, ignoring case.
+    pub fn by_name(name: &str) -> Option<PhyProfile> {
+        PROFILES
+            .iter()
+            .find(|profile| profile.name.eq_ignore_ascii_case(name))
+            .copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    pub fn t_s(&self) -> Time {
+        self.t_s
+    }
+
+    pub fn t_w(&self) -> Time {
+        self.t_w
+    }
+
+    pub fn t_q(&self) -> Time {
+        self.t_q
+    }
+
+    pub fn t_r(&self) -> Time {
+        self.t_r
+    }
+
+    /// Line rate in b/s.
+    pub fn capacity(&self) -> f64 {
+        self.capacity
+    }
+
+    /// Time to transmit a byte at the line rate, in ns.
+    pub fn byte_time(&self) -> f64 {
+        1e9 * 8.0 / self.capacity
+    }
+
+    /// Time to transmit `size` bytes at the line rate.
+    pub fn tx_time(&self, size: u32) -> Time {
+        tx_time(size, self.byte_time())
+    }
+
+    /// Power draw in W while active.
+    pub fn power(&self) -> f64 {
+        self.power
+    }
+
+    /// Power draw in W while in LPI.
+    pub fn lpi_power(&self) -> f64 {
+        self.lpi_power
+    }
+
+    pub fn with_ts(self, t_s: Time) -> PhyProfile {
+        PhyProfile { t_s, ..self }
+    }
+
+    pub fn with_tw(self, t_w: Time) -> PhyProfile {
+        PhyProfile { t_w, ..self }
+    }
+
+    pub fn with_capacity(self, capacity: f64) -> PhyProfile {
+        PhyProfile { capacity, ..self }
+    }
+
+    pub fn with_power(self, power: f64, lpi_power: f64) -> PhyProfile {
+        PhyProfile {
+            power,
+            lpi_power,
+            ..self
+        }
+    }
+}
+
+impl Default for PhyProfile {
+    fn default() -> PhyProfile {
+        PhyProfile::by_name("10gbase-t").unwrap()
+    }
+}
+
+impl Display for PhyProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl FromStr for PhyProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PhyProfile::by_name(s).ok_or_else(|| {
+            let names: Vec<&str> = PROFILES.iter().map(|profile| profile.name).collect();
+            format!("unknown PHY \"{}\" (known: {})", s, names.join(", "))
+        })
+    }
+}
+
+/// Transmission time of `size` bytes, rounded to the ns. Shared with the
+/// switch, which keeps the byte time around.
+pub(crate) fn tx_time(size: u32, byte_time: f64) -> Time {
+    Time((f64::from(size) * byte_time).round() as u64)
+}
@@ -0,0 +1,327 @@
+use crate::phy::PhyProfile;
+use crate::power::PowerModel;
+use crate::simulator::Time;
+use crate::switch::{Packet, Status};
+
+/// When to leave LPI.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wake {
+    /// Wake up at the given time, no matter what arrives later.
+    At(Time),
+    /// Wake up at the given time at the latest. Packets arriving before it
+    /// may bring the wake up forward.
+    By(Time),
+}
+
+impl Wake {
+    pub fn time(self) -> Time {
+        match self {
+            Wake::At(time) | Wake::By(time) => time,
+        }
+    }
+}
+
+/// Decides when the link enters and leaves LPI.
+pub trait SleepPolicy {
+    /// Called every time a transmission ends at `idle_since`. Returns when
+    /// to start entering LPI if no packet arrives before, or `None` to stay
+    /// active.
+    fn sleep_at(&mut self, idle_since: Time) -> Option<Time>;
+
+    /// Called while in LPI with the packets queued so far, oldest first.
+    /// The queue is never empty, but its last packet may not have arrived
+    /// yet.
+    fn wake_at(&mut self, queue: &[Packet]) -> Wake;
+
+    /// Called with the length of every idle period, from the end of a
+    /// transmission to the arrival of the next packet.
+    fn idle_gap(&mut self, _gap: Time) {}
+
+    /// Hysteresis currently in use, for policies that adapt it.
+    fn hysteresis(&self) -> Option<Time> {
+        None
+    }
+}
+
+/// The default policy. Enters LPI `hyst` after the last transmission and
+/// leaves it `idle` after the first packet arrives to the queue.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimerPolicy {
+    hyst: Time,
+    idle: Time,
+}
+
+impl TimerPolicy {
+    pub fn new(hyst: Time, idle: Time) -> TimerPolicy {
+        TimerPolicy { hyst, idle }
+    }
+}
+
+impl SleepPolicy for TimerPolicy {
+    fn sleep_at(&mut self, idle_since: Time) -> Option<Time> {
+        Some(idle_since + self.hyst)
+    }
+
+    fn wake_at(&mut self, queue: &[Packet]) -> Wake {
+        Wake::At(queue[0].arrival() + self.idle)
+    }
+}
+
+/// Leaves LPI as soon as the queue holds `packets` packets or `bytes`
+/// bytes, if that happens before the wrapped policy wakes up the link.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Coalescing<P: SleepPolicy> {
+    policy: P,
+    packets: Option<usize>,
+    bytes: Option<u64>,
+}
+
+impl<P: SleepPolicy> Coalescing<P> {
+    pub fn new(policy: P, packets: Option<usize>, bytes: Option<u64>) -> Coalescing<P> {
+        Coalescing {
+            policy,
+            packets,
+            bytes,
+        }
+    }
+
+    /// Arrival time of the packet that makes `queue` cross a threshold.
+    fn crossing(&self, queue: &[Packet]) -> Option<Time> {
+        let mut bytes = 0;
+
+        for (i, packet) in queue.iter().enumerate() {
+            bytes += u64::from(packet.size());
+            if self.packets.is_some_and(|limit| i + 1 >= limit)
+                || self.bytes.is_some_and(|limit| bytes >= limit)
+            {
+                return Some(packet.arrival());
+            }
+        }
+
+        None
+    }
+}
+
+impl<P: SleepPolicy> SleepPolicy for Coalescing<P> {
+    fn sleep_at(&mut self, idle_since: Time) -> Option<Time> {
+        self.policy.sleep_at(idle_since)
+    }
+
+    fn idle_gap(&mut self, gap: Time) {
+        self.policy.idle_gap(gap)
+    }
+
+    fn hysteresis(&self) -> Option<Time> {
+        self.policy.hysteresis()
+    }
+
+    fn wake_at(&mut self, queue: &[Packet]) -> Wake {
+        let wake = self.policy.wake_at(queue);
+
+        match self.crossing(queue) {
+            Some(crossing) if crossing <= wake.time() => Wake::At(crossing),
+            Some(_) => wake,
+            None => Wake::By(wake.time()),
+        }
+    }
+}
+
+impl SleepPolicy for Box<dyn SleepPolicy> {
+    fn sleep_at(&mut self, idle_since: Time) -> Option<Time> {
+        self.as_mut().sleep_at(idle_since)
+    }
+
+    fn wake_at(&mut self, queue: &[Packet]) -> Wake {
+        self.as_mut().wake_at(queue)
+    }
+
+    fn idle_gap(&mut self, gap: Time) {
+        self.as_mut().idle_gap(gap)
+    }
+
+    fn hysteresis(&self) -> Option<Time> {
+        self.as_ref().hysteresis()
+    }
+}
+
+/// Chooses the hysteresis from an exponentially weighted moving average of
+/// the idle periods. When the predicted idle period is longer than the
+/// break-even time, Ts + Tw, the link enters LPI right after the queue
+/// drains. Otherwise it waits for the break-even time before doing so.
+/// Leaves LPI `idle` after the first packet arrives to the queue.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AdaptivePolicy {
+    idle: Time,
+    break_even: Time,
+    alpha: f64,
+    estimate: Option<f64>,
+    hyst: Time,
+}
+
+impl AdaptivePolicy {
+    /// `alpha` is the weight of every new idle period in the average.
+    pub fn new(phy: &PhyProfile, idle: Time, alpha: f64) -> AdaptivePolicy {
+        assert!(
+            alpha > 0.0 && alpha <= 1.0,
+            "The average weight must be in (0, 1]"
+        );
+        let break_even = phy.t_s() + phy.t_w();
+
+        AdaptivePolicy {
+            idle,
+            break_even,
+            alpha,
+            estimate: None,
+            hyst: break_even,
+        }
+    }
+
+    /// Predicted length of the next idle period.
+    pub fn estimate(&self) -> Option<Time> {
+        self.estimate.map(|estimate| Time(estimate.round() as u64))
+    }
+}
+
+impl SleepPolicy for AdaptivePolicy {
+    fn sleep_at(&mut self, idle_since: Time) -> Option<Time> {
+        Some(idle_since + self.hyst)
+    }
+
+    fn wake_at(&mut self, queue: &[Packet]) -> Wake {
+        Wake::At(queue[0].arrival() + self.idle)
+    }
+
+    fn idle_gap(&mut self, gap: Time) {
+        let gap = gap.0 as f64;
+        let estimate = match self.estimate {
+            Some(estimate) => self.alpha * gap + (1.0 - self.alpha) * estimate,
+            None => gap,
+        };
+
+        self.estimate = Some(estimate);
+        self.hyst = if estimate > self.break_even.0 as f64 {
+            Time(0)
+        } else {
+            self.break_even
+        };
+    }
+
+    fn hysteresis(&self) -> Option<Time> {
+        Some(self.hyst)
+    }
+}
+
+/// Knows every arrival in advance. Enters LPI right after a transmission if
+/// the link stays idle long enough for that to save energy, and leaves it
+/// `Tw` before the next arrival, adding no delay. The energy of such a run is
+/// a lower bound for the other policies, refresh periods aside.
+#[derive(Clone, Debug, PartialEq)]
+pub struct OraclePolicy {
+    arrivals: Vec<Time>,
+    next: usize,
+    t_w: Time,
+    /// Shortest idle period worth sleeping through, if any
+    min_gap: Option<Time>,
+}
+
+impl OraclePolicy {
+    /// `arrivals` must be in time order.
+    pub fn new(
+        phy: &PhyProfile,
+        power: &PowerModel,
+        arrivals: impl IntoIterator<Item = Time>,
+    ) -> OraclePolicy {
+        let (t_s, t_w) = (phy.t_s(), phy.t_w());
+        let on = power.power(Status::On);
+        let off = power.power(Status::Off);
+
+        // Sleeping through a gap g costs Ts at the TOff power, Tw at the TOn
+        // power and the rest at the LPI power, instead of g at the active one
+        let min_gap = (off < on).then(|| {
+            let extra = t_s.0 as f64 * (power.power(Status::TOff) - off)
+                + t_w.0 as f64 * (power.power(Status::TOn) - off);
+            Time((extra / (on - off)).max(0.0).round() as u64).max(t_s + t_w)
+        });
+
+        OraclePolicy {
+            arrivals: arrivals.into_iter().collect(),
+            next: 0,
+            t_w,
+            min_gap,
+        }
+    }
+
+    /// Idle periods shorter than this are not worth sleeping through, or
+    /// `None` if LPI never saves energy.
+    pub fn min_gap(&self) -> Option<Time> {
+        self.min_gap
+    }
+}
+
+impl SleepPolicy for OraclePolicy {
+    fn sleep_at(&mut self, idle_since: Time) -> Option<Time> {
+        let min_gap = self.min_gap?;
+        while self
+            .arrivals
+            .get(self.next)
+            .is_some_and(|arrival| *arrival < idle_since)
+        {
+            self.next += 1;
+        }
+
+        match self.arrivals.get(self.next) {
+            Some(arrival) if *arrival - idle_since <= min_gap => None,
+            _ => Some(idle_since),
+        }
+    }
+
+    fn wake_at(&mut self, queue: &[Packet]) -> Wake {
+        Wake::At(Time(queue[0].arrival().0.saturating_sub(self.t_w.0)))
+    }
+}
+
+/// Leaves LPI as late as it can while still transmitting every queued
+/// packet within `deadline` of its arrival, allowing for `Tw` and the
+/// transmission of the packets ahead of it. Enters LPI `hyst` after the last
+/// transmission. The bound holds as long as the deadline leaves room for the
+/// transitions and the transmissions of the queued packets.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeadlinePolicy {
+    phy: PhyProfile,
+    hyst: Time,
+    deadline: Time,
+}
+
+impl DeadlinePolicy {
+    pub fn new(phy: &PhyProfile, hyst: Time, deadline: Time) -> DeadlinePolicy {
+        DeadlinePolicy {
+            phy: *phy,
+            hyst,
+            deadline,
+        }
+    }
+}
+
+impl SleepPolicy for DeadlinePolicy {
+    fn sleep_at(&mut self, idle_since: Time) -> Option<Time> {
+        Some(idle_since + self.hyst)
+    }
+
+    fn wake_at(&mut self, queue: &[Packet]) -> Wake {
+        let mut wake: Option<Time> = None;
+        let mut busy = self.phy.t_w();
+
+        for packet in queue {
+            // Packets arriving after the wake up do not change it
+            if wake.is_some_and(|wake| packet.arrival() > wake) {
+                break;
+            }
+
+            busy = busy + self.phy.tx_time(packet.size());
+            let latest = Time((packet.arrival() + self.deadline).0.saturating_sub(busy.0));
+            wake = Some(wake.map_or(latest, |wake| wake.min(latest)));
+        }
+
+        Wake::By(wake.expect("The queue is never empty in LPI"))
+    }
+}
@@ -0,0 +1,132 @@
+use crate::phy::PhyProfile;
+use crate::simulator::Time;
+use crate::switch::Status;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// A power figure, either absolute in W or relative to the active power.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Power {
+    Watts(f64),
+    Fraction(f64),
+}
+
+impl Power {
+    /// Absolute value in W given the active power.
+    pub fn watts(self, active: f64) -> f64 {
+        match self {
+            Power::Watts(watts) => watts,
+            Power::Fraction(fraction) => fraction * active,
+        }
+    }
+}
+
+/// Error returned when a `Power` cannot be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsePowerError(String);
+
+impl Display for ParsePowerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is not a valid power. Use a non-negative number of W or a percentage",
+            self.0
+        )
+    }
+}
+
+impl Error for ParsePowerError {}
+
+impl FromStr for Power {
+    type Err = ParsePowerError;
+
+    /// Parses `"0.4"` or `"0.4W"` as watts and `"10%"` as a fraction of the
+    /// active power. Values must be finite and not negative.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let value = |value: &str| match f64::from_str(value.trim()) {
+            Ok(value) if value.is_finite() && value >= 0.0 => Ok(value),
+            _ => Err(ParsePowerError(s.to_string())),
+        };
+        let s = s.trim();
+
+        match s.strip_suffix('%') {
+            Some(percent) => Ok(Power::Fraction(value(percent)? / 100.0)),
+            None => Ok(Power::Watts(value(s.strip_suffix('W').unwrap_or(s))?)),
+        }
+    }
+}
+
+/// Power draw of a link, in W, in each of its states.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PowerModel {
+    on: f64,
+    off: f64,
+    t_on: f64,
+    t_off: f64,
+    refresh: f64,
+}
+
+impl PowerModel {
+    /// Refresh periods draw the same power as the active state unless
+    /// changed with `with_power`.
+    pub fn new(on: f64, off: f64, t_on: f64, t_off: f64) -> PowerModel {
+        PowerModel {
+            on,
+            off,
+            t_on,
+            t_off,
+            refresh: on,
+        }
+    }
+
+    /// Power model of a PHY. Transitions and refresh periods draw the same
+    /// power as the active state.
+    pub fn from_phy(phy: &PhyProfile) -> PowerModel {
+        PowerModel::new(phy.power(), phy.lpi_power(), phy.power(), phy.power())
+    }
+
+    /// Overrides the power drawn in `status`. Fractions are relative to the
+    /// current active power, so the `On` state should be set first.
+    pub fn with_power(self, status: Status, power: Power) -> PowerModel {
+        let watts = power.watts(self.on);
+
+        match status {
+            Status::On => PowerModel { on: watts, ..self },
+            Status::Off => PowerModel { off: watts, ..self },
+            Status::TOn => PowerModel {
+                t_on: watts,
+                ..self
+            },
+            Status::TOff => PowerModel {
+                t_off: watts,
+                ..self
+            },
+            Status::Refresh => PowerModel {
+                refresh: watts,
+                ..self
+            },
+        }
+    }
+
+    /// Power drawn in `status` in W.
+    pub fn power(&self, status: Status) -> f64 {
+        match status {
+            Status::On => self.on,
+            Status::Off => self.off,
+            Status::TOn => self.t_on,
+            Status::TOff => self.t_off,
+            Status::Refresh => self.refresh,
+        }
+    }
+
+    /// Energy, in J, consumed while staying `time` in `status`.
+    pub fn energy(&self, status: Status, time: Time) -> f64 {
+        self.power(status) * time.as_secs()
+    }
+
+    /// Energy, in J, that a link that never sleeps consumes during `time`.
+    pub fn always_on_energy(&self, time: Time) -> f64 {
+        self.energy(Status::On, time)
+    }
+}
@@ -0,0 +1,31 @@
+use super::Time;
+use crate::switch::{Packet, Status};
+
+/// Something that happened during a simulation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimEvent {
+    /// A packet reached the switch.
+    Arrival { time: Time, packet: Packet },
+    /// A packet finished its transmission.
+    Departure { time: Time, packet: Packet },
+    /// The link went from one state to another.
+    StateChange {
+        time: Time,
+        from: Status,
+        to: Status,
+    },
+    /// A packet arrived to a full buffer and was discarded. Reported once
+    /// the switch reaches it, so it may be later than events after its time.
+    Drop { time: Time, packet: Packet },
+}
+
+impl SimEvent {
+    pub fn time(&self) -> Time {
+        match *self {
+            SimEvent::Arrival { time, .. }
+            | SimEvent::Departure { time, .. }
+            | SimEvent::StateChange { time, .. }
+            | SimEvent::Drop { time, .. } => time,
+        }
+    }
+}
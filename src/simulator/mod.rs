@@ -1,36 +1,65 @@
+mod event;
 mod time;
 
-pub use self::time::Time;
-use crate::switch::{Packet, Status, Switch};
+pub use self::event::SimEvent;
+pub use self::time::{ParseTimeError, Time};
+use crate::phy::PhyProfile;
+use crate::policy::SleepPolicy;
+use crate::switch::{BufferLimit, Packet, Refresh, Status, Switch};
+use std::collections::VecDeque;
 use std::iter::Iterator;
 
+/// Simulates the link for the input packets, producing `SimEvent`s in time
+/// order, except for drops.
 pub struct Simulator<I: Iterator<Item = Packet>> {
     input: I,
     switch: Switch,
     current_time: Time,
     next_packet: Option<Packet>,
+    /// Read from the input to report their arrival, not yet given to the
+    /// switch
+    ahead: VecDeque<Packet>,
+    /// Given to the switch or read ahead, with their arrival not reported
+    arrivals: VecDeque<Packet>,
+    report_arrivals: bool,
+    /// Switch event waiting for the arrivals before it
+    pending: Option<SimEvent>,
+    status: Status,
+    dropped: u64,
 }
 
-impl<I: Iterator<Item = Packet>> Iterator for Simulator<I> {
-    type Item = (Time, Option<Packet>, Option<Status>);
+/// A simulation event in the tuple form of `Simulator::tuples`: its time,
+/// the packet that departed, if any, and the new status of the switch, if
+/// it changed.
+pub type Event = (Time, Option<Packet>, Option<Status>);
 
-    fn next(&mut self) -> Option<(Time, Option<Packet>, Option<Status>)> {
-        match self.next_packet {
-            Some(packet) => {
-                let arrival_time = packet.arrival();
-                let res = self.process();
+/// A step of the switch: an `Event` or the packet that was dropped.
+type Step = (Time, Option<Packet>, Option<Status>, Option<Packet>);
+
+impl<I: Iterator<Item = Packet>> Iterator for Simulator<I> {
+    type Item = SimEvent;
 
-                if self.current_time >= arrival_time {
-                    self.next_packet = self.input.next();
-                    if self.next_packet.is_some() {
-                        self.switch.add_packet(&self.next_packet.unwrap());
+    fn next(&mut self) -> Option<SimEvent> {
+        let ev = match self.pending.take() {
+            Some(ev) => ev,
+            None => loop {
+                match self.step() {
+                    Some(ev) => {
+                        if let Some(ev) = self.convert(ev) {
+                            break ev;
+                        }
                     }
+                    None => return self.next_arrival(None),
                 }
+            },
+        };
 
-                Some(res)
+        match self.next_arrival(Some(ev.time())) {
+            Some(arrival) => {
+                self.pending = Some(ev);
+                Some(arrival)
             }
-            None if self.switch.is_empty() => None,
-            None => Some(self.process()),
+            None => Some(ev),
         }
     }
 }
@@ -42,38 +71,155 @@ impl<I: Iterator<Item = Packet>> Simulator<I> {
         Simulator::new_internal(input, switch)
     }
 
-    pub fn new_explicit(
-        hyst: Time,
-        idle: Time,
-        input: I,
-        ts: Time,
-        tw: Time,
-        capacity: f64,
-    ) -> Simulator<I> {
-        let switch = Switch::new_explicit(hyst, idle, ts, tw, capacity);
+    pub fn new_explicit(hyst: Time, idle: Time, input: I, phy: PhyProfile) -> Simulator<I> {
+        let switch = Switch::with_profile(hyst, idle, phy);
 
         Simulator::new_internal(input, switch)
     }
 
+    /// Limits the transmit buffer. Packets arriving to a full buffer are
+    /// dropped.
+    pub fn with_buffer(mut self, buffer: BufferLimit) -> Simulator<I> {
+        self.switch.set_buffer(buffer);
+
+        self
+    }
+
+    /// Models the refresh periods inside LPI.
+    pub fn with_refresh(mut self, refresh: Refresh) -> Simulator<I> {
+        self.switch.set_refresh(refresh);
+
+        self
+    }
+
+    /// Replaces the hysteresis and delay timers with a custom policy to
+    /// decide when to enter and leave LPI.
+    pub fn with_policy<P: SleepPolicy + 'static>(mut self, policy: P) -> Simulator<I> {
+        self.switch.set_policy(Box::new(policy));
+
+        self
+    }
+
+    /// Number of packets dropped so far because the buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn policy(&self) -> &dyn SleepPolicy {
+        self.switch.policy()
+    }
+
     fn new_internal(mut input: I, switch: Switch) -> Simulator<I> {
         let packet = input.next();
 
+        let status = switch.status();
         let mut s = Simulator {
             input,
             current_time: Time(0),
             switch,
             next_packet: packet,
+            ahead: VecDeque::new(),
+            arrivals: packet.into_iter().collect(),
+            report_arrivals: true,
+            pending: None,
+            status,
+            dropped: 0,
         };
-        if s.next_packet.is_some() {
-            s.switch.add_packet(&s.next_packet.unwrap());
+        if let Some(packet) = s.next_packet {
+            s.switch.add_packet(&packet);
         }
 
         s
     }
 
-    fn process(&mut self) -> (Time, Option<Packet>, Option<Status>) {
+    /// Produces the events in the former tuple form, which does not report
+    /// drops.
+    pub fn tuples(mut self) -> Tuples<I> {
+        self.report_arrivals = false;
+        self.arrivals.clear();
+
+        Tuples(self)
+    }
+
+    fn step(&mut self) -> Option<Step> {
+        match self.next_packet {
+            Some(_) => {
+                let res = self.process();
+
+                // The switch decides the admission of every packet that has
+                // arrived before it moves past its arrival
+                while self
+                    .next_packet
+                    .is_some_and(|packet| packet.arrival() <= self.current_time)
+                {
+                    self.next_packet = self.next_input();
+                    if let Some(next) = self.next_packet {
+                        self.switch.add_packet(&next);
+                    }
+                }
+
+                Some(res)
+            }
+            None if self.switch.is_empty() => None,
+            None => Some(self.process()),
+        }
+    }
+
+    fn next_input(&mut self) -> Option<Packet> {
+        if let Some(packet) = self.ahead.pop_front() {
+            return Some(packet);
+        }
+
+        let packet = self.input.next();
+        if self.report_arrivals {
+            self.arrivals.extend(packet);
+        }
+
+        packet
+    }
+
+    /// The arrival of the next packet, if it is not after `time`.
+    fn next_arrival(&mut self, time: Option<Time>) -> Option<SimEvent> {
+        if self.arrivals.is_empty() {
+            let packet = self.input.next()?;
+            self.ahead.push_back(packet);
+            self.arrivals.push_back(packet);
+        }
+
+        let packet = *self.arrivals.front()?;
+        if time.is_some_and(|time| packet.arrival() > time) {
+            return None;
+        }
+        self.arrivals.pop_front();
+
+        Some(SimEvent::Arrival {
+            time: packet.arrival(),
+            packet,
+        })
+    }
+
+    /// Switch events have a single departure, state change or drop.
+    fn convert(&mut self, ev: Step) -> Option<SimEvent> {
+        match ev {
+            (time, Some(packet), _, _) => Some(SimEvent::Departure { time, packet }),
+            (time, _, _, Some(packet)) => Some(SimEvent::Drop { time, packet }),
+            // Staying in the same state is not a change
+            (time, _, Some(to), _) if to != self.status => {
+                let from = self.status;
+                self.status = to;
+                Some(SimEvent::StateChange { time, from, to })
+            }
+            _ => None,
+        }
+    }
+
+    fn process(&mut self) -> Step {
         let res = self.switch.advance(self.current_time);
 
+        if res.dropped().is_some() {
+            self.dropped += 1;
+            return (res.time(), None, None, res.dropped());
+        }
         self.current_time = res.time();
 
         let status = if res.state_change() {
@@ -82,6 +228,32 @@ impl<I: Iterator<Item = Packet>> Simulator<I> {
             None
         };
 
-        (res.time(), res.packet(), status)
+        (res.time(), res.packet(), status, None)
+    }
+}
+
+/// Adapts a `Simulator` to produce the events as tuples.
+pub struct Tuples<I: Iterator<Item = Packet>>(Simulator<I>);
+
+impl<I: Iterator<Item = Packet>> Tuples<I> {
+    pub fn dropped(&self) -> u64 {
+        self.0.dropped()
+    }
+
+    pub fn policy(&self) -> &dyn SleepPolicy {
+        self.0.policy()
+    }
+}
+
+impl<I: Iterator<Item = Packet>> Iterator for Tuples<I> {
+    type Item = Event;
+
+    fn next(&mut self) -> Option<Event> {
+        loop {
+            match self.0.step()? {
+                (_, None, None, Some(_)) => continue,
+                (time, packet, status, _) => return Some((time, packet, status)),
+            }
+        }
     }
 }
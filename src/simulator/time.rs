@@ -1,8 +1,9 @@
-use std::num::ParseIntError;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
 use std::ops::{Add, Div, Sub};
 use std::str::FromStr;
 
-#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy, Debug, Default)]
 pub struct Time(pub u64);
 
 impl Time {
@@ -15,13 +16,98 @@ impl Time {
     }
 }
 
+/// Units accepted when parsing, with their length in ns.
+const UNITS: [(&str, u64); 6] = [
+    ("ns", 1),
+    ("us", 1_000),
+    ("\u{b5}s", 1_000),
+    ("\u{3bc}s", 1_000),
+    ("ms", 1_000_000),
+    ("s", 1_000_000_000),
+];
+
+/// Units used for display, largest first.
+const DISPLAY_UNITS: [(&str, u64); 4] = [
+    ("s", 1_000_000_000),
+    ("ms", 1_000_000),
+    ("us", 1_000),
+    ("ns", 1),
+];
+
+/// Error returned when a `Time` cannot be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseTimeError(String);
+
+impl Display for ParseTimeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} is not a valid time. Use an integer number of ns or a value with a unit: ns, us, ms or s",
+            self.0
+        )
+    }
+}
+
+impl Error for ParseTimeError {}
+
 impl FromStr for Time {
-    type Err = ParseIntError;
+    type Err = ParseTimeError;
 
+    /// Parses a value with a unit, such as `"4480ns"`, `"10us"`, `"1.5ms"`
+    /// or `"2s"`. Values with no unit are an integer number of ns.
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let val = u64::from_str(s)?;
+        let err = || ParseTimeError(s.to_string());
+        let s = s.trim();
+        let split = s
+            .find(|c: char| c.is_alphabetic() && c != 'e' && c != 'E')
+            .unwrap_or(s.len());
+        let (value, unit) = (s[..split].trim(), s[split..].trim());
+
+        let scale = match unit {
+            "" => 1,
+            _ => UNITS
+                .iter()
+                .find(|(name, _)| *name == unit)
+                .map(|(_, scale)| *scale)
+                .ok_or_else(err)?,
+        };
+
+        if let Ok(value) = u64::from_str(value) {
+            return value.checked_mul(scale).map(Time).ok_or_else(err);
+        }
+        match f64::from_str(value) {
+            // Fractional values need a unit to be told apart from the old
+            // integer format
+            Ok(value) if !unit.is_empty() && value.is_finite() && value >= 0.0 => {
+                let ns = (value * scale as f64).round();
+                if ns < u64::MAX as f64 {
+                    Ok(Time(ns as u64))
+                } else {
+                    Err(err())
+                }
+            }
+            _ => Err(err()),
+        }
+    }
+}
+
+impl Display for Time {
+    /// Writes the time in the largest unit in which it is at least one,
+    /// e.g. `4.48us`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (unit, scale) = DISPLAY_UNITS
+            .iter()
+            .find(|(_, scale)| self.0 >= *scale)
+            .unwrap_or(&("ns", 1));
+        let (whole, frac) = (self.0 / scale, self.0 % scale);
 
-        Ok(Time(val))
+        if frac == 0 {
+            write!(f, "{}{}", whole, unit)
+        } else {
+            let digits = scale.ilog10() as usize;
+            let frac = format!("{:0digits$}", frac, digits = digits);
+            write!(f, "{}.{}{}", whole, frac.trim_end_matches('0'), unit)
+        }
     }
 }
 
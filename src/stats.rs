@@ -0,0 +1,771 @@
+use crate::phy::PhyProfile;
+use crate::simulator::{SimEvent, Time};
+use crate::switch::{Packet, Status};
+use std::collections::{BTreeMap, VecDeque};
+
+/// Collects the time spent in every state and the transitions between them
+/// from the simulator events. The switch starts in `Off` at time zero, and
+/// the accounting ends at the last event.
+#[derive(Default)]
+pub struct StateStats {
+    state: Status,
+    times: [Time; 5],
+    transitions: [[u64; 5]; 5],
+    end: Time,
+}
+
+impl StateStats {
+    pub fn new() -> StateStats {
+        StateStats::default()
+    }
+
+    pub fn record(&mut self, event: &SimEvent) {
+        // Drops are reported late, and their arrival is reported too
+        if let SimEvent::Drop { .. } = event {
+            return;
+        }
+
+        let time = event.time();
+        if time > self.end {
+            self.times[self.state.index()] = self.times[self.state.index()] + (time - self.end);
+            self.end = time;
+        }
+        if let SimEvent::StateChange { from, to, .. } = *event {
+            self.transitions[from.index()][to.index()] += 1;
+            self.state = to;
+        }
+    }
+
+    /// Time from zero to the last event.
+    pub fn span(&self) -> Time {
+        self.end
+    }
+
+    pub fn time(&self, state: Status) -> Time {
+        self.times[state.index()]
+    }
+
+    /// Time spent in every state, in the order of `Status::ALL`.
+    pub fn times(&self) -> impl Iterator<Item = (Status, Time)> + '_ {
+        Status::ALL
+            .into_iter()
+            .map(|state| (state, self.time(state)))
+    }
+
+    /// Number of changes from the `from` to the `to` state.
+    pub fn transitions(&self, from: Status, to: Status) -> u64 {
+        self.transitions[from.index()][to.index()]
+    }
+
+    /// Every transition that happened with its count, ordered by the
+    /// original and then the new state as in `Status::ALL`.
+    pub fn all_transitions(&self) -> impl Iterator<Item = (Status, Status, u64)> + '_ {
+        Status::ALL
+            .into_iter()
+            .flat_map(|from| Status::ALL.into_iter().map(move |to| (from, to)))
+            .map(|(from, to)| (from, to, self.transitions(from, to)))
+            .filter(|(_, _, count)| *count > 0)
+    }
+
+    /// Times the link started to enter LPI.
+    pub fn lpi_entries(&self) -> u64 {
+        Status::ALL
+            .into_iter()
+            .map(|state| self.transitions(state, Status::TOff))
+            .sum()
+    }
+
+    /// Times the link started to leave LPI.
+    pub fn wake_ups(&self) -> u64 {
+        Status::ALL
+            .into_iter()
+            .map(|state| self.transitions(state, Status::TOn))
+            .sum()
+    }
+}
+
+/// Parts of the delay of a packet, by what it was waiting for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DelayBreakdown {
+    /// Waiting for the link to finish entering LPI, in `TOff`.
+    pub sleep: Time,
+    /// Waiting in LPI, quiet or refreshing, for the link to start waking up.
+    pub idle: Time,
+    /// Waiting for the link to leave LPI, in `TOn`.
+    pub wake: Time,
+    /// Waiting for the transmission of earlier packets.
+    pub queue: Time,
+    /// Its own transmission.
+    pub transmission: Time,
+}
+
+impl DelayBreakdown {
+    /// The parts with their names.
+    pub fn parts(&self) -> [(&'static str, Time); 5] {
+        [
+            ("sleep", self.sleep),
+            ("idle", self.idle),
+            ("wake", self.wake),
+            ("queue", self.queue),
+            ("transmission", self.transmission),
+        ]
+    }
+
+    pub fn total(&self) -> Time {
+        self.sleep + self.idle + self.wake + self.queue + self.transmission
+    }
+
+    fn add(&mut self, other: &DelayBreakdown) {
+        self.sleep = self.sleep + other.sleep;
+        self.idle = self.idle + other.idle;
+        self.wake = self.wake + other.wake;
+        self.queue = self.queue + other.queue;
+        self.transmission = self.transmission + other.transmission;
+    }
+}
+
+/// Splits the delay of every packet by what it was waiting for, from the
+/// simulator events. A packet first waits through the states the link goes
+/// through until it can be transmitted, when the link is on and done with
+/// earlier packets.
+pub struct BreakdownStats {
+    /// Start of every state since the arrival of the next packet to leave
+    changes: VecDeque<(Time, Status)>,
+    on_since: Time,
+    last_departure: Time,
+    total: DelayBreakdown,
+    count: usize,
+}
+
+impl Default for BreakdownStats {
+    fn default() -> BreakdownStats {
+        BreakdownStats {
+            changes: VecDeque::from([(Time(0), Status::Off)]),
+            on_since: Time(0),
+            last_departure: Time(0),
+            total: DelayBreakdown::default(),
+            count: 0,
+        }
+    }
+}
+
+impl BreakdownStats {
+    pub fn new() -> BreakdownStats {
+        BreakdownStats::default()
+    }
+
+    /// Returns the breakdown of departing packets.
+    pub fn record(&mut self, event: &SimEvent) -> Option<DelayBreakdown> {
+        match *event {
+            SimEvent::StateChange { time, to, .. } => {
+                self.changes.push_back((time, to));
+                if to == Status::On {
+                    self.on_since = time;
+                }
+                None
+            }
+            SimEvent::Departure { time, packet } => {
+                let breakdown = self.breakdown(time, &packet);
+
+                self.last_departure = time;
+                self.total.add(&breakdown);
+                self.count += 1;
+                // Later packets arrive later, so older states are done with
+                while self
+                    .changes
+                    .get(1)
+                    .is_some_and(|(start, _)| *start <= packet.arrival())
+                {
+                    self.changes.pop_front();
+                }
+
+                Some(breakdown)
+            }
+            _ => None,
+        }
+    }
+
+    fn breakdown(&self, departure: Time, packet: &Packet) -> DelayBreakdown {
+        let arrival = packet.arrival();
+        let start = arrival.max(self.last_departure).max(self.on_since);
+        let mut breakdown = DelayBreakdown {
+            transmission: departure - start,
+            ..DelayBreakdown::default()
+        };
+
+        for (i, (from, state)) in self.changes.iter().enumerate() {
+            let to = self.changes.get(i + 1).map_or(start, |(to, _)| *to);
+            let (from, to) = ((*from).max(arrival), to.min(start));
+            if to <= from {
+                continue;
+            }
+
+            let part = match state {
+                Status::TOff => &mut breakdown.sleep,
+                Status::Off | Status::Refresh => &mut breakdown.idle,
+                Status::TOn => &mut breakdown.wake,
+                Status::On => &mut breakdown.queue,
+            };
+            *part = *part + (to - from);
+        }
+
+        breakdown
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Sum of the breakdowns of every packet.
+    pub fn total(&self) -> DelayBreakdown {
+        self.total
+    }
+
+    /// Share of the total delay of every part, in percent, in the order of
+    /// `DelayBreakdown::parts`.
+    pub fn shares(&self) -> Option<[(&'static str, f64); 5]> {
+        let total = self.total.total();
+        if total == Time(0) {
+            return None;
+        }
+
+        Some(
+            self.total
+                .parts()
+                .map(|(name, time)| (name, 100.0 * (time / total))),
+        )
+    }
+}
+
+/// A wake-up of the link, from LPI to the next wake-up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WakeUp {
+    /// When the link started to leave LPI.
+    pub time: Time,
+    /// The oldest packet waiting, which the link woke up for.
+    pub cause: Packet,
+    /// Packets transmitted until the next wake-up.
+    pub packets: u64,
+}
+
+/// Wake-ups caused by the packets of a flow.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FlowWakes {
+    /// `None` for the packets without a known flow.
+    pub flow: Option<u32>,
+    pub wakes: u64,
+    /// Packets transmitted in the wake cycles caused by the flow.
+    pub packets: u64,
+}
+
+/// Records the packet that caused every wake-up, from the simulator
+/// events, and how many packets were transmitted before the next one.
+/// Policies that know the future may wake up before the packet arrives,
+/// which then causes the wake-up.
+#[derive(Default)]
+pub struct WakeStats {
+    /// Arrived packets not yet transmitted or dropped
+    waiting: VecDeque<Packet>,
+    /// Wake-up waiting for the arrival of its cause
+    early: Option<Time>,
+    wakes: Vec<WakeUp>,
+}
+
+impl WakeStats {
+    pub fn new() -> WakeStats {
+        WakeStats::default()
+    }
+
+    pub fn record(&mut self, event: &SimEvent) {
+        match *event {
+            SimEvent::Arrival { packet, .. } => {
+                if let Some(time) = self.early.take() {
+                    self.wakes.push(WakeUp {
+                        time,
+                        cause: packet,
+                        packets: 0,
+                    });
+                }
+                self.waiting.push_back(packet);
+            }
+            SimEvent::Departure { packet, .. } => {
+                self.remove(&packet);
+                if let Some(wake) = self.wakes.last_mut() {
+                    wake.packets += 1;
+                }
+            }
+            // Drops are reported late, maybe after later departures
+            SimEvent::Drop { packet, .. } => self.remove(&packet),
+            SimEvent::StateChange {
+                time,
+                to: Status::TOn,
+                ..
+            } => match self.waiting.front() {
+                Some(&cause) => self.wakes.push(WakeUp {
+                    time,
+                    cause,
+                    packets: 0,
+                }),
+                None => self.early = Some(time),
+            },
+            SimEvent::StateChange { .. } => (),
+        }
+    }
+
+    fn remove(&mut self, packet: &Packet) {
+        if let Some(i) = self.waiting.iter().position(|waiting| waiting == packet) {
+            self.waiting.remove(i);
+        }
+    }
+
+    /// Every wake-up, in time order.
+    pub fn wakes(&self) -> &[WakeUp] {
+        &self.wakes
+    }
+
+    /// Mean packets transmitted in a wake cycle.
+    pub fn mean_packets(&self) -> Option<f64> {
+        if self.wakes.is_empty() {
+            return None;
+        }
+
+        let packets: u64 = self.wakes.iter().map(|wake| wake.packets).sum();
+        Some(packets as f64 / self.wakes.len() as f64)
+    }
+
+    /// Wake-ups by the flow of their cause, the packets without a flow
+    /// first and then by flow.
+    pub fn by_flow(&self) -> Vec<FlowWakes> {
+        let mut flows: BTreeMap<Option<u32>, FlowWakes> = BTreeMap::new();
+        for wake in &self.wakes {
+            let flow = wake.cause.flow();
+            let entry = flows.entry(flow).or_insert(FlowWakes {
+                flow,
+                wakes: 0,
+                packets: 0,
+            });
+            entry.wakes += 1;
+            entry.packets += wake.packets;
+        }
+
+        flows.into_values().collect()
+    }
+}
+
+/// Times collected in any order, sorted when needed for their percentiles.
+#[derive(Default)]
+struct Samples {
+    values: Vec<Time>,
+    sorted: bool,
+}
+
+impl Samples {
+    fn push(&mut self, value: Time) {
+        self.values.push(value);
+        self.sorted = false;
+    }
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Time> + '_ {
+        self.values.iter().copied()
+    }
+
+    /// The values in increasing order.
+    fn sorted(&mut self) -> &[Time] {
+        if !self.sorted {
+            self.values.sort_unstable();
+            self.sorted = true;
+        }
+
+        &self.values
+    }
+
+    /// Nearest-rank percentile: the smallest value with at least `p` percent
+    /// of the values not above it.
+    fn percentile(&mut self, p: f64) -> Option<Time> {
+        let values = self.sorted();
+        if values.is_empty() {
+            return None;
+        }
+
+        let rank = (p / 100.0 * values.len() as f64).ceil() as usize;
+        Some(values[rank.clamp(1, values.len()) - 1])
+    }
+}
+
+/// Collects the delay, from arrival to departure, of every packet. The
+/// delays themselves are only kept, for the percentiles and the histogram,
+/// if asked to.
+#[derive(Default)]
+pub struct DelayStats {
+    count: usize,
+    total: Time,
+    min: Option<Time>,
+    max: Option<Time>,
+    delays: Option<Samples>,
+}
+
+impl DelayStats {
+    pub fn new() -> DelayStats {
+        DelayStats::default()
+    }
+
+    /// Also keeps the delay of every packet.
+    pub fn with_samples(self) -> DelayStats {
+        DelayStats {
+            delays: Some(Samples::default()),
+            ..self
+        }
+    }
+
+    pub fn record(&mut self, departure: Time, packet: &Packet) {
+        let delay = departure - packet.arrival();
+
+        self.count += 1;
+        self.total = self.total + delay;
+        self.min = Some(self.min.map_or(delay, |min| min.min(delay)));
+        self.max = Some(self.max.map_or(delay, |max| max.max(delay)));
+        if let Some(delays) = self.delays.as_mut() {
+            delays.push(delay);
+        }
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<Time> {
+        self.min
+    }
+
+    pub fn max(&self) -> Option<Time> {
+        self.max
+    }
+
+    /// Mean delay in seconds.
+    pub fn mean(&self) -> Option<f64> {
+        match self.count() {
+            0 => None,
+            n => Some(self.total.as_secs() / n as f64),
+        }
+    }
+
+    /// Percentile of the delays, with `p` in percent, e.g. `99.9`. `None` if
+    /// they were not kept.
+    pub fn percentile(&mut self, p: f64) -> Option<Time> {
+        self.delays.as_mut()?.percentile(p)
+    }
+
+    /// Number of packets whose delay falls in each `bin` wide interval,
+    /// starting at zero. Returns the lower limit of every bin with its count,
+    /// or nothing if no delays were kept.
+    pub fn histogram(&mut self, bin: Time) -> Vec<(Time, usize)> {
+        assert!(bin > Time(0), "Histogram bins must have a positive width");
+        let delays = self.delays.as_mut().map_or(&[][..], Samples::sorted);
+
+        let mut hist: Vec<(Time, usize)> = Vec::new();
+        for delay in delays {
+            let start = Time(delay.0 / bin.0 * bin.0);
+            match hist.last_mut() {
+                Some((last, count)) if *last == start => *count += 1,
+                _ => hist.push((start, 1)),
+            }
+        }
+
+        hist
+    }
+}
+
+/// Collects the idle gaps of a trace on a link that never enters LPI,
+/// transmitting every packet at the line rate as soon as it can. Gaps
+/// longer than the break-even time, `Ts + Tw`, are the ones where entering
+/// LPI can save energy.
+pub struct GapStats {
+    phy: PhyProfile,
+    gaps: Samples,
+    packets: u64,
+    start: Option<Time>,
+    last_departure: Time,
+    busy: Time,
+}
+
+impl GapStats {
+    pub fn new(phy: PhyProfile) -> GapStats {
+        GapStats {
+            phy,
+            gaps: Samples::default(),
+            packets: 0,
+            start: None,
+            last_departure: Time(0),
+            busy: Time(0),
+        }
+    }
+
+    pub fn record(&mut self, packet: &Packet) {
+        let tx_time = self.phy.tx_time(packet.size());
+        let start = match self.start {
+            None => {
+                self.start = Some(packet.arrival());
+                packet.arrival()
+            }
+            Some(_) if packet.arrival() > self.last_departure => {
+                self.gaps.push(packet.arrival() - self.last_departure);
+                packet.arrival()
+            }
+            Some(_) => self.last_departure,
+        };
+
+        self.last_departure = start + tx_time;
+        self.busy = self.busy + tx_time;
+        self.packets += 1;
+    }
+
+    pub fn packets(&self) -> u64 {
+        self.packets
+    }
+
+    /// Time from the first arrival to the last departure.
+    pub fn span(&self) -> Time {
+        self.start
+            .map_or(Time(0), |start| self.last_departure - start)
+    }
+
+    /// Time spent transmitting.
+    pub fn busy(&self) -> Time {
+        self.busy
+    }
+
+    /// Time spent between transmissions.
+    pub fn idle(&self) -> Time {
+        self.span() - self.busy
+    }
+
+    /// `Ts + Tw` of the PHY.
+    pub fn break_even(&self) -> Time {
+        self.phy.t_s() + self.phy.t_w()
+    }
+
+    pub fn count(&self) -> usize {
+        self.gaps.len()
+    }
+
+    pub fn max(&self) -> Option<Time> {
+        self.gaps.iter().max()
+    }
+
+    /// Mean gap in seconds.
+    pub fn mean(&self) -> Option<f64> {
+        match self.count() {
+            0 => None,
+            n => Some(self.idle().as_secs() / n as f64),
+        }
+    }
+
+    /// Percentile of the gaps, with `p` in percent.
+    pub fn percentile(&mut self, p: f64) -> Option<Time> {
+        self.gaps.percentile(p)
+    }
+
+    /// Gaps longer than the break-even time.
+    pub fn over_break_even(&self) -> impl Iterator<Item = Time> + '_ {
+        let break_even = self.break_even();
+        self.gaps.iter().filter(move |gap| *gap > break_even)
+    }
+
+    /// Share of the idle time in gaps longer than the break-even time, in
+    /// percent.
+    pub fn over_break_even_pct(&self) -> Option<f64> {
+        let idle = self.idle();
+        if idle == Time(0) {
+            return None;
+        }
+
+        let over = self.over_break_even().fold(Time(0), |sum, gap| sum + gap);
+        Some(100.0 * (over / idle))
+    }
+
+    /// Share of the time the link could spend in LPI knowing every arrival
+    /// in advance, in percent. It enters LPI in every gap longer than the
+    /// break-even time, and leaves it just in time for the next packet.
+    pub fn best_lpi_pct(&self) -> Option<f64> {
+        let span = self.span();
+        if span == Time(0) {
+            return None;
+        }
+
+        let break_even = self.break_even();
+        let lpi = self
+            .over_break_even()
+            .fold(Time(0), |sum, gap| sum + (gap - break_even));
+        Some(100.0 * (lpi / span))
+    }
+
+    /// Number and total length of the gaps in every decade, from 10ns, with
+    /// the shorter gaps in the first one. Returns the lower limit of every
+    /// non-empty decade with its count and length.
+    pub fn distribution(&mut self) -> Vec<(Time, usize, Time)> {
+        let mut dist: Vec<(Time, usize, Time)> = Vec::new();
+        for gap in self.gaps.sorted() {
+            let start = match gap.0 {
+                0..=9 => Time(0),
+                ns => Time(10u64.pow(ns.ilog10())),
+            };
+            match dist.last_mut() {
+                Some((last, count, total)) if *last == start => {
+                    *count += 1;
+                    *total = *total + *gap;
+                }
+                _ => dist.push((start, 1, *gap)),
+            }
+        }
+
+        dist
+    }
+}
+
+/// Queue length at a point in time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QueueSample {
+    pub time: Time,
+    pub packets: usize,
+    pub bytes: u64,
+}
+
+/// Time-weighted occupancy of the switch queue, rebuilt from the
+/// departures. A packet is in the queue from its arrival until the end of
+/// its transmission. Dropped packets never enter it.
+#[derive(Default)]
+pub struct QueueStats {
+    /// Departures already recorded but still in the future
+    pending: VecDeque<(Time, u32)>,
+    packets: usize,
+    bytes: u64,
+    last_change: Time,
+    packet_area: u128,
+    byte_area: u128,
+    max_packets: usize,
+    max_bytes: u64,
+    /// Time spent with every queue length, in packets
+    occupancy: Vec<Time>,
+    series: Option<Vec<QueueSample>>,
+}
+
+impl QueueStats {
+    pub fn new() -> QueueStats {
+        QueueStats::default()
+    }
+
+    /// Also keeps the queue length after every change.
+    pub fn with_series(self) -> QueueStats {
+        QueueStats {
+            series: Some(Vec::new()),
+            ..self
+        }
+    }
+
+    /// Records a packet that left the switch at `departure`. Packets must be
+    /// recorded in departure order.
+    pub fn record(&mut self, departure: Time, packet: &Packet) {
+        // Every departure up to this arrival belongs to an earlier packet,
+        // so it has already been recorded
+        self.depart_until(packet.arrival());
+
+        self.advance(packet.arrival());
+        self.packets += 1;
+        self.bytes += u64::from(packet.size());
+        self.max_packets = self.max_packets.max(self.packets);
+        self.max_bytes = self.max_bytes.max(self.bytes);
+        self.sample();
+
+        self.pending.push_back((departure, packet.size()));
+    }
+
+    /// Applies the remaining departures and accounts for the time until
+    /// `end`, if later.
+    pub fn finish(&mut self, end: Time) {
+        self.depart_until(Time(u64::MAX));
+        if end > self.last_change {
+            self.advance(end);
+        }
+    }
+
+    /// Time-weighted mean queue length in packets.
+    pub fn mean_packets(&self) -> Option<f64> {
+        match self.last_change {
+            Time(0) => None,
+            total => Some(self.packet_area as f64 / total.0 as f64),
+        }
+    }
+
+    /// Time-weighted mean queue length in bytes.
+    pub fn mean_bytes(&self) -> Option<f64> {
+        match self.last_change {
+            Time(0) => None,
+            total => Some(self.byte_area as f64 / total.0 as f64),
+        }
+    }
+
+    pub fn max_packets(&self) -> usize {
+        self.max_packets
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+
+    /// Fraction of the time spent with every queue length, in packets,
+    /// starting at an empty queue.
+    pub fn distribution(&self) -> Vec<(usize, f64)> {
+        self.occupancy
+            .iter()
+            .enumerate()
+            .map(|(packets, time)| (packets, *time / self.last_change))
+            .collect()
+    }
+
+    /// Queue length after every change, if kept.
+    pub fn series(&self) -> Option<&[QueueSample]> {
+        self.series.as_deref()
+    }
+
+    fn depart_until(&mut self, time: Time) {
+        while let Some(&(departure, size)) = self.pending.front() {
+            if departure > time {
+                break;
+            }
+            self.pending.pop_front();
+
+            self.advance(departure);
+            self.packets -= 1;
+            self.bytes -= u64::from(size);
+            self.sample();
+        }
+    }
+
+    fn advance(&mut self, time: Time) {
+        let elapsed = time - self.last_change;
+
+        self.packet_area += u128::from(elapsed.0) * self.packets as u128;
+        self.byte_area += u128::from(elapsed.0) * u128::from(self.bytes);
+        if self.occupancy.len() <= self.packets {
+            self.occupancy.resize(self.packets + 1, Time(0));
+        }
+        self.occupancy[self.packets] = self.occupancy[self.packets] + elapsed;
+        self.last_change = time;
+    }
+
+    fn sample(&mut self) {
+        let sample = QueueSample {
+            time: self.last_change,
+            packets: self.packets,
+            bytes: self.bytes,
+        };
+
+        if let Some(series) = self.series.as_mut() {
+            series.push(sample);
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use crate::simulator::Time;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+/// A parameter that can be swept.
+pub trait Sweepable: Copy + PartialOrd + Sized {
+    fn parse(s: &str) -> Result<Self, String>;
+
+    /// Values from `start` to `end`, both included, `step` apart. `None` if
+    /// the range is empty or the step is not positive.
+    fn range(start: Self, end: Self, step: Self) -> Option<Vec<Self>>;
+}
+
+impl Sweepable for f64 {
+    fn parse(s: &str) -> Result<f64, String> {
+        f64::from_str(s.trim()).map_err(|_| format!("{} is not a valid number", s))
+    }
+
+    fn range(start: f64, end: f64, step: f64) -> Option<Vec<f64>> {
+        if step <= 0.0 || end < start {
+            return None;
+        }
+
+        // Tolerate rounding errors so that the end is included
+        let steps = ((end - start) / step + 1e-9).floor() as usize;
+        // Round to drop the binary representation errors, so that
+        // 0:1:0.1 yields 0.3 and not 0.30000000000000004
+        Some(
+            (0..=steps)
+                .map(|i| ((start + i as f64 * step) * 1e9).round() / 1e9)
+                .collect(),
+        )
+    }
+}
+
+impl Sweepable for Time {
+    fn parse(s: &str) -> Result<Time, String> {
+        Time::from_str(s).map_err(|err| err.to_string())
+    }
+
+    fn range(start: Time, end: Time, step: Time) -> Option<Vec<Time>> {
+        if step == Time(0) || end < start {
+            return None;
+        }
+
+        Some(
+            (0..=(end - start).0 / step.0)
+                .map(|i| Time(start.0 + i * step.0))
+                .collect(),
+        )
+    }
+}
+
+/// Values taken by a swept parameter. Parsed from a comma separated list of
+/// single values and inclusive `start:end:step` ranges, e.g. `"0,10:50:20"`
+/// is 0, 10, 30 and 50. Times may have units, as in `"0:10us:2500ns"`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Values<T = f64>(Vec<T>);
+
+impl<T: Sweepable> Values<T> {
+    pub fn new(values: Vec<T>) -> Values<T> {
+        Values(values)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        self.0.iter().copied()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<T: Sweepable> FromStr for Values<T> {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut values = Vec::new();
+
+        for item in s.split(',') {
+            let bounds: Vec<&str> = item.split(':').collect();
+
+            match bounds[..] {
+                [value] => values.push(T::parse(value)?),
+                [start, end, step] => {
+                    let range = T::range(T::parse(start)?, T::parse(end)?, T::parse(step)?)
+                        .ok_or_else(|| {
+                            format!(
+                                "{} is not a valid range. Use start:end:step with a positive step",
+                                item
+                            )
+                        })?;
+                    values.extend(range);
+                }
+                _ => {
+                    return Err(format!(
+                        "{} is neither a value nor a start:end:step range",
+                        item
+                    ))
+                }
+            }
+        }
+
+        Ok(Values(values))
+    }
+}
+
+/// Calls `run` for every job in `threads` worker threads. Returns the
+/// results in the same order as the jobs.
+pub fn run<T, R, F>(jobs: &[T], threads: usize, run: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(jobs.len()));
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, jobs.len().max(1)) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= jobs.len() {
+                    break;
+                }
+
+                let result = run(&jobs[i]);
+                results.lock().unwrap().push((i, result));
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_unstable_by_key(|(i, _)| *i);
+
+    results.into_iter().map(|(_, result)| result).collect()
+}
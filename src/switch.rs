@@ -1,21 +1,30 @@
+use crate::phy::{self, PhyProfile};
+use crate::policy::{SleepPolicy, TimerPolicy, Wake};
 use crate::simulator::Time;
 use std::cmp;
 use std::collections::VecDeque;
 use std::fmt::{Display, Error, Formatter};
 
-const T_S: Time = Time(2880);
-const T_W: Time = Time(4480);
-const CAPACITY: f64 = 10e9;
-
 #[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
 pub struct Packet {
     arrival: Time,
     size: u32,
+    flow: Option<u32>,
 }
 
 impl Packet {
     pub fn new(arrival: Time, size: u32) -> Packet {
-        Packet { arrival, size }
+        Packet {
+            arrival,
+            size,
+            flow: None,
+        }
+    }
+
+    /// Marks the packet as part of `flow`.
+    pub fn with_flow(mut self, flow: u32) -> Packet {
+        self.flow = Some(flow);
+        self
     }
 
     pub fn arrival(&self) -> Time {
@@ -25,69 +34,201 @@ impl Packet {
     pub fn size(&self) -> u32 {
         self.size
     }
+
+    /// Flow the packet belongs to, if known.
+    pub fn flow(&self) -> Option<u32> {
+        self.flow
+    }
+}
+
+/// Size of the transmit buffer. Only packets waiting for transmission take
+/// room in it, the one being transmitted does not.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BufferLimit {
+    packets: Option<usize>,
+    bytes: Option<u64>,
+}
+
+impl BufferLimit {
+    /// A buffer holding at most `packets` packets and `bytes` bytes. `None`
+    /// leaves that dimension unbounded.
+    pub fn new(packets: Option<usize>, bytes: Option<u64>) -> BufferLimit {
+        BufferLimit { packets, bytes }
+    }
+
+    pub fn unlimited() -> BufferLimit {
+        BufferLimit::default()
+    }
+
+    fn admits(&self, packets: usize, bytes: u64, size: u32) -> bool {
+        self.packets.is_none_or(|limit| packets < limit)
+            && self
+                .bytes
+                .is_none_or(|limit| bytes + u64::from(size) <= limit)
+    }
+}
+
+/// How refresh periods inside LPI are modelled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Refresh {
+    /// LPI is a single quiet period.
+    #[default]
+    Disabled,
+    /// LPI alternates quiet (Tq) and refresh (Tr) periods. Wake ups
+    /// interrupt refreshes.
+    Interruptible,
+    /// Like `Interruptible`, but wake ups wait for the ongoing refresh to
+    /// finish.
+    Blocking,
 }
 
 pub struct Switch {
-    t_s: Time,
-    t_w: Time,
+    phy: PhyProfile,
     byte_time: f64,
-    hyst: Time,
-    idle: Time,
-    status: Option<Box<dyn SwitchStatus>>,
+    policy: Box<dyn SleepPolicy>,
+    status: State,
     queue: VecDeque<Packet>,
+    buffer: BufferLimit,
+    refresh: Refresh,
+    admitted: usize,
+    admitted_bytes: u64,
+    last_departure: Option<Time>,
 }
 
 impl Switch {
     pub fn new(hyst: Time, idle: Time) -> Switch {
-        Switch::new_explicit(hyst, idle, T_S, T_W, CAPACITY)
+        Switch::with_profile(hyst, idle, PhyProfile::default())
     }
 
     pub fn new_explicit(hyst: Time, idle: Time, ts: Time, tw: Time, capacity: f64) -> Switch {
+        let phy = PhyProfile::default()
+            .with_ts(ts)
+            .with_tw(tw)
+            .with_capacity(capacity);
+
+        Switch::with_profile(hyst, idle, phy)
+    }
+
+    pub fn with_profile(hyst: Time, idle: Time, phy: PhyProfile) -> Switch {
         Switch {
-            t_s: ts,
-            t_w: tw,
-            byte_time: 1e9 * 8.0 / capacity,
-            hyst,
-            idle,
-            status: Some(Box::new(Off::new(Time(0)))),
+            phy,
+            byte_time: phy.byte_time(),
+            policy: Box::new(TimerPolicy::new(hyst, idle)),
+            status: State::Off(Off::new(Time(0))),
             queue: VecDeque::new(),
+            buffer: BufferLimit::unlimited(),
+            refresh: Refresh::Disabled,
+            admitted: 0,
+            admitted_bytes: 0,
+            last_departure: None,
         }
     }
 
+    pub fn set_buffer(&mut self, buffer: BufferLimit) {
+        self.buffer = buffer;
+    }
+
+    /// Profiles without quiet nor refresh periods, Tq + Tr = 0, never
+    /// refresh.
+    pub fn set_refresh(&mut self, refresh: Refresh) {
+        self.refresh = if self.phy.t_q() + self.phy.t_r() == Time(0) {
+            Refresh::Disabled
+        } else {
+            refresh
+        };
+    }
+
+    /// Replaces the policy deciding when to enter and leave LPI.
+    pub fn set_policy(&mut self, policy: Box<dyn SleepPolicy>) {
+        self.policy = policy;
+    }
+
     fn tx_time(&self, packet: &Packet) -> Time {
-        Time((f64::from(packet.size()) * self.byte_time).round() as u64)
+        phy::tx_time(packet.size(), self.byte_time)
     }
 
+    /// Queues a packet. Packets may be added ahead of their arrival time, so
+    /// whether they fit in the buffer is only decided once the switch
+    /// reaches it.
     pub fn add_packet(&mut self, packet: &Packet) {
         self.queue.push_back(*packet);
     }
 
+    fn pop_packet(&mut self) -> Packet {
+        let packet = self.queue.pop_front().unwrap();
+
+        assert!(self.admitted > 0, "Transmitting a packet not in the buffer");
+        self.admitted -= 1;
+        self.admitted_bytes -= u64::from(packet.size());
+
+        if let Some(last_departure) = self.last_departure {
+            if packet.arrival() > last_departure {
+                self.policy.idle_gap(packet.arrival() - last_departure);
+            }
+        }
+
+        packet
+    }
+
+    /// Decides the admission of the packets that have arrived by `now`.
+    /// Returns the first one that does not fit in the buffer, if any.
+    fn admit(&mut self, now: Time) -> Option<Packet> {
+        while let Some(&packet) = self.queue.get(self.admitted) {
+            if packet.arrival() > now {
+                break;
+            }
+
+            if !self
+                .buffer
+                .admits(self.admitted, self.admitted_bytes, packet.size())
+            {
+                return self.queue.remove(self.admitted);
+            }
+            self.admitted += 1;
+            self.admitted_bytes += u64::from(packet.size());
+        }
+
+        None
+    }
+
+    pub fn phy(&self) -> &PhyProfile {
+        &self.phy
+    }
+
+    pub fn policy(&self) -> &dyn SleepPolicy {
+        self.policy.as_ref()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.queue.is_empty()
     }
 
     pub fn status(&self) -> Status {
-        self.status.as_ref().unwrap().state()
+        self.status.state()
     }
 
     pub fn advance(&mut self, now: Time) -> SwitchEvent {
-        let res;
-
-        if let Some(mut state) = self.status.take() {
-            let ev = state.advance(now, self);
-            res = SwitchEvent::new(&ev);
-            self.status = Some(ev.status);
-        } else {
-            panic!("Switch is not in any state.");
+        if let Some(dropped) = self.admit(now) {
+            return SwitchEvent {
+                time: dropped.arrival(),
+                packet: None,
+                dropped: Some(dropped),
+                state_change: false,
+            };
         }
 
-        res
+        let mut state = self.status;
+        let ev = state.advance(now, self);
+        self.status = ev.status;
+
+        SwitchEvent::new(&ev)
     }
 }
 
 pub struct SwitchEvent {
     time: Time,
     packet: Option<Packet>,
+    dropped: Option<Packet>,
     state_change: bool,
 }
 
@@ -96,6 +237,7 @@ impl SwitchEvent {
         SwitchEvent {
             time: swev.time,
             packet: swev.packet,
+            dropped: None,
             state_change: swev.state_change,
         }
     }
@@ -107,24 +249,56 @@ impl SwitchEvent {
     pub fn packet(&self) -> Option<Packet> {
         self.packet
     }
+
+    /// Packet that arrived to a full buffer. The event time is then its
+    /// arrival time, which may precede the time of the previous event.
+    pub fn dropped(&self) -> Option<Packet> {
+        self.dropped
+    }
+
     pub fn state_change(&self) -> bool {
         self.state_change
     }
 }
 
 struct Event {
-    status: Box<dyn SwitchStatus>,
+    status: State,
     time: Time,
     packet: Option<Packet>,
     state_change: bool,
 }
 
-#[derive(Hash, PartialEq, Eq, Clone, Copy)]
+#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug, Default)]
 pub enum Status {
+    #[default]
     Off,
     On,
     TOff,
     TOn,
+    /// Refresh period while in LPI. `Off` stands for the quiet periods.
+    Refresh,
+}
+
+impl Status {
+    /// Every state, in the order they are reported.
+    pub const ALL: [Status; 5] = [
+        Status::On,
+        Status::Off,
+        Status::TOn,
+        Status::TOff,
+        Status::Refresh,
+    ];
+
+    /// Position in `ALL`.
+    pub fn index(self) -> usize {
+        match self {
+            Status::On => 0,
+            Status::Off => 1,
+            Status::TOn => 2,
+            Status::TOff => 3,
+            Status::Refresh => 4,
+        }
+    }
 }
 
 impl Display for Status {
@@ -137,6 +311,7 @@ impl Display for Status {
                 Status::On => "ON",
                 Status::TOff => "T_OFF",
                 Status::TOn => "T_ON",
+                Status::Refresh => "REFRESH",
             }
         )
     }
@@ -148,42 +323,205 @@ trait SwitchStatus {
     fn state(&self) -> Status;
 }
 
+/// State of the switch with the data it needs. Kept inline rather than
+/// boxed, so that moving from one state to the next does not allocate.
+#[derive(Clone, Copy, Debug)]
+enum State {
+    Off(Off),
+    Refresh(LpiRefresh),
+    TOn(TOn),
+    On(On),
+    TOff(TOff),
+}
+
+impl SwitchStatus for State {
+    fn advance(&mut self, now: Time, switch: &mut Switch) -> Event {
+        match self {
+            State::Off(state) => state.advance(now, switch),
+            State::Refresh(state) => state.advance(now, switch),
+            State::TOn(state) => state.advance(now, switch),
+            State::On(state) => state.advance(now, switch),
+            State::TOff(state) => state.advance(now, switch),
+        }
+    }
+
+    fn state(&self) -> Status {
+        match self {
+            State::Off(state) => state.state(),
+            State::Refresh(state) => state.state(),
+            State::TOn(state) => state.state(),
+            State::On(state) => state.state(),
+            State::TOff(state) => state.state(),
+        }
+    }
+}
+
+/// Next step while in LPI: keep waiting for arrivals until the given time,
+/// or leave LPI at it.
+enum LpiStep {
+    Wait(Time),
+    Wake(Time),
+}
+
+/// What to do next while in LPI since `last_event`, having seen `seen` queued
+/// packets so far.
+fn lpi_step(switch: &mut Switch, last_event: Time, seen: usize) -> LpiStep {
+    let queue: &[Packet] = switch.queue.make_contiguous();
+
+    assert!(
+        !queue.is_empty(),
+        "Cannot run if Off state with empty queue"
+    );
+
+    match switch.policy.wake_at(queue) {
+        Wake::At(wake) => LpiStep::Wake(cmp::max(wake, last_event)),
+        Wake::By(wake) => {
+            // Wait for the next arrival unless the policy wakes up first or
+            // no more packets came since the last time.
+            let last = queue[queue.len() - 1].arrival;
+            if last < wake && queue.len() > seen {
+                LpiStep::Wait(cmp::max(last, last_event))
+            } else {
+                LpiStep::Wake(cmp::max(wake, last_event))
+            }
+        }
+    }
+}
+
+/// Start and end of the first refresh period that ends after `time` for an
+/// LPI period that started at `lpi_start`.
+fn refresh_period(switch: &Switch, lpi_start: Time, time: Time) -> (Time, Time) {
+    let (t_q, t_r) = (switch.phy.t_q(), switch.phy.t_r());
+    let offset = Time((time - lpi_start).0 % (t_q + t_r).0);
+    let start = if offset < t_q {
+        time + (t_q - offset)
+    } else {
+        time - (offset - t_q)
+    };
+
+    (start, start + t_r)
+}
+
+#[derive(Clone, Copy, Debug)]
 struct Off {
     last_event: Time,
+    lpi_start: Time,
+    seen: usize,
 }
 
 impl Off {
     fn new(last_event: Time) -> Off {
-        Off { last_event }
+        Off {
+            last_event,
+            lpi_start: last_event,
+            seen: 0,
+        }
     }
 }
 
 impl SwitchStatus for Off {
     fn advance(&mut self, _now: Time, switch: &mut Switch) -> Event {
-        let queue = &switch.queue;
+        let step = lpi_step(switch, self.last_event, self.seen);
+        let next = match step {
+            LpiStep::Wait(time) | LpiStep::Wake(time) => time,
+        };
 
-        assert!(
-            !queue.is_empty(),
-            "Cannot run if Off state with empty queue"
-        );
+        if switch.refresh != Refresh::Disabled {
+            let (refresh_start, _) = refresh_period(switch, self.lpi_start, self.last_event);
+            if refresh_start < next {
+                return Event {
+                    time: refresh_start,
+                    status: State::Refresh(LpiRefresh {
+                        last_event: refresh_start,
+                        lpi_start: self.lpi_start,
+                        seen: self.seen,
+                    }),
+                    packet: None,
+                    state_change: true,
+                };
+            }
+        }
 
-        let next_state = cmp::max(queue[0].arrival + switch.idle, self.last_event);
+        self.last_event = next;
+        match step {
+            LpiStep::Wait(_) => {
+                self.seen = switch.queue.len();
+                Event {
+                    time: self.last_event,
+                    status: State::Off(Off { ..*self }),
+                    packet: None,
+                    state_change: false,
+                }
+            }
+            LpiStep::Wake(_) => Event {
+                time: self.last_event,
+                status: State::TOn(TOn::new(next)),
+                packet: None,
+                state_change: true,
+            },
+        }
+    }
 
-        self.last_event = next_state;
+    fn state(&self) -> Status {
+        Status::Off
+    }
+}
+
+/// Refresh period inside LPI.
+#[derive(Clone, Copy, Debug)]
+struct LpiRefresh {
+    last_event: Time,
+    lpi_start: Time,
+    seen: usize,
+}
+
+impl SwitchStatus for LpiRefresh {
+    fn advance(&mut self, _now: Time, switch: &mut Switch) -> Event {
+        let (_, refresh_end) = refresh_period(switch, self.lpi_start, self.last_event);
+
+        let (time, status): (Time, State) = match lpi_step(switch, self.last_event, self.seen) {
+            LpiStep::Wait(time) if time < refresh_end => {
+                self.last_event = time;
+                self.seen = switch.queue.len();
+                return Event {
+                    time,
+                    status: State::Refresh(LpiRefresh { ..*self }),
+                    packet: None,
+                    state_change: false,
+                };
+            }
+            LpiStep::Wake(time) if time <= refresh_end => {
+                let time = match switch.refresh {
+                    Refresh::Blocking => refresh_end,
+                    _ => time,
+                };
+                (time, State::TOn(TOn::new(time)))
+            }
+            _ => (
+                refresh_end,
+                State::Off(Off {
+                    last_event: refresh_end,
+                    lpi_start: self.lpi_start,
+                    seen: self.seen,
+                }),
+            ),
+        };
+        self.last_event = time;
 
         Event {
-            time: self.last_event,
-            status: Box::new(TOn::new(next_state)),
+            time,
+            status,
             packet: None,
             state_change: true,
         }
     }
 
     fn state(&self) -> Status {
-        Status::Off
+        Status::Refresh
     }
 }
 
+#[derive(Clone, Copy, Debug)]
 struct TOn {
     last_event: Time,
 }
@@ -207,12 +545,12 @@ impl SwitchStatus for TOn {
             "Cannot run if T_On state with empty queue"
         );
 
-        let next_state = self.last_event + switch.t_w;
+        let next_state = self.last_event + switch.phy.t_w();
         self.last_event = next_state;
 
         Event {
             time: self.last_event,
-            status: Box::new(On::new(next_state)),
+            status: State::On(On::new(next_state)),
             packet: None,
             state_change: true,
         }
@@ -222,14 +560,14 @@ impl SwitchStatus for TOn {
 #[derive(Clone, Copy, Debug)]
 struct On {
     last_event: Time,
-    hyst_end: Time,
+    hyst_end: Option<Time>,
 }
 
 impl On {
     fn new(last_event: Time) -> On {
         On {
             last_event,
-            hyst_end: last_event,
+            hyst_end: None,
         }
     }
 }
@@ -240,18 +578,20 @@ impl SwitchStatus for On {
     }
 
     fn advance(&mut self, now: Time, switch: &mut Switch) -> Event {
-        let packet = {
-            let queue = &mut switch.queue;
+        {
+            let queue = &switch.queue;
 
             assert!(!queue.is_empty());
 
             if queue[0].arrival() > now {
-                let new_state: (Time, Box<dyn SwitchStatus>) = if queue[0].arrival() > self.hyst_end
-                {
-                    (self.hyst_end, Box::new(TOff::new(self.hyst_end)))
-                } else {
-                    self.last_event = queue[0].arrival();
-                    (self.last_event, Box::new(*self))
+                let new_state: (Time, State) = match self.hyst_end {
+                    Some(hyst_end) if queue[0].arrival() > hyst_end => {
+                        (hyst_end, State::TOff(TOff::new(hyst_end)))
+                    }
+                    _ => {
+                        self.last_event = queue[0].arrival();
+                        (self.last_event, State::On(*self))
+                    }
                 };
                 return Event {
                     time: new_state.0,
@@ -260,24 +600,28 @@ impl SwitchStatus for On {
                     state_change: true,
                 };
             }
-
-            queue.pop_front().unwrap()
-        };
+        }
+        let packet = switch.pop_packet();
 
         let next_event = self.last_event + switch.tx_time(&packet);
-        self.hyst_end = next_event + switch.hyst;
+        switch.last_departure = Some(next_event);
+        self.hyst_end = switch
+            .policy
+            .sleep_at(next_event)
+            .map(|hyst_end| cmp::max(hyst_end, next_event));
 
         self.last_event = next_event;
 
         Event {
             time: self.last_event,
-            status: Box::new(*self),
+            status: State::On(*self),
             packet: Some(packet),
             state_change: false,
         }
     }
 }
 
+#[derive(Clone, Copy, Debug)]
 struct TOff {
     last_event: Time,
 }
@@ -294,12 +638,12 @@ impl SwitchStatus for TOff {
     }
 
     fn advance(&mut self, _now: Time, switch: &mut Switch) -> Event {
-        let next_state = self.last_event + switch.t_s;
+        let next_state = self.last_event + switch.phy.t_s();
         self.last_event = next_state;
 
         Event {
             time: self.last_event,
-            status: Box::new(Off::new(next_state)),
+            status: State::Off(Off::new(next_state)),
             packet: None,
             state_change: true,
         }
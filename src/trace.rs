@@ -0,0 +1,349 @@
+use crate::simulator::Time;
+use crate::switch::Packet;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::io::{self, BufRead};
+
+/// An error found while reading a text trace or a capture. Lines are
+/// numbered from one. In captures, the line is the number of the packet.
+#[derive(Debug)]
+pub enum TraceError {
+    /// The input could not be read.
+    Io { line: usize, source: io::Error },
+    /// The line does not have exactly two fields.
+    Malformed { line: usize, text: String },
+    /// The arrival time is not a non-negative number of seconds.
+    InvalidTime { line: usize, text: String },
+    /// The size is not a number of bytes.
+    InvalidSize { line: usize, text: String },
+    /// The arrival time is earlier than that of a previous packet.
+    Backwards {
+        line: usize,
+        arrival: Time,
+        previous: Time,
+    },
+}
+
+impl TraceError {
+    pub fn line(&self) -> usize {
+        match self {
+            TraceError::Io { line, .. }
+            | TraceError::Malformed { line, .. }
+            | TraceError::InvalidTime { line, .. }
+            | TraceError::InvalidSize { line, .. }
+            | TraceError::Backwards { line, .. } => *line,
+        }
+    }
+}
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TraceError::Io { line, source } => {
+                write!(f, "line {}: could not read input: {}", line, source)
+            }
+            TraceError::Malformed { line, text } => {
+                write!(f, "line {}: malformed line \"{}\"", line, text)
+            }
+            TraceError::InvalidTime { line, text } => {
+                write!(f, "line {}: {} is not a valid arrival time", line, text)
+            }
+            TraceError::InvalidSize { line, text } => {
+                write!(f, "line {}: {} is not a valid size", line, text)
+            }
+            TraceError::Backwards {
+                line,
+                arrival,
+                previous,
+            } => write!(
+                f,
+                "line {}: arrival time {:e}s precedes the previous one, {:e}s",
+                line,
+                arrival.as_secs(),
+                previous.as_secs()
+            ),
+        }
+    }
+}
+
+impl Error for TraceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TraceError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Largest time, in ns, parsed without floating point. Up to this value,
+/// rounding a `f64` number of seconds times 1e9 always gives back an exact
+/// integer number of ns, so both ways agree.
+const MAX_EXACT_NS: u64 = 1_000_000_000_000_000;
+
+/// Parses an arrival time in seconds, as `Time::from_secs` would do with
+/// the nearest `f64`. Plain decimals and exponent notation whose value is an
+/// exact number of ns are parsed as fixed point, the rest as `f64`.
+pub fn parse_secs(text: &[u8]) -> Option<Time> {
+    match parse_exact_ns(text) {
+        Some(ns) => Some(Time(ns)),
+        None => match std::str::from_utf8(text).ok()?.parse::<f64>() {
+            Ok(secs) if secs.is_finite() && secs >= 0.0 => Some(Time::from_secs(secs)),
+            _ => None,
+        },
+    }
+}
+
+fn parse_exact_ns(text: &[u8]) -> Option<u64> {
+    let mut mantissa: u64 = 0;
+    let mut digits = 0;
+    let mut frac_digits = 0i32;
+    let mut point = false;
+    let mut i = 0;
+
+    while let Some(&c) = text.get(i) {
+        match c {
+            b'0'..=b'9' => {
+                // Leading zeros add no precision
+                if mantissa > 0 || c != b'0' {
+                    digits += 1;
+                }
+                if digits > 18 {
+                    return None;
+                }
+                mantissa = mantissa * 10 + u64::from(c - b'0');
+                if point {
+                    frac_digits += 1;
+                }
+            }
+            b'.' if !point => point = true,
+            _ => break,
+        }
+        i += 1;
+    }
+    if i == 0 || (point && i == 1) {
+        return None;
+    }
+
+    let mut exp = 0i32;
+    if let Some(b'e' | b'E') = text.get(i) {
+        let (negative, start) = match text.get(i + 1) {
+            Some(b'-') => (true, i + 2),
+            Some(b'+') => (false, i + 2),
+            _ => (false, i + 1),
+        };
+        let exp_digits = text.get(start..)?;
+        if exp_digits.is_empty() || exp_digits.len() > 3 {
+            return None;
+        }
+        for &c in exp_digits {
+            if !c.is_ascii_digit() {
+                return None;
+            }
+            exp = exp * 10 + i32::from(c - b'0');
+        }
+        if negative {
+            exp = -exp;
+        }
+    } else if i != text.len() {
+        return None;
+    }
+
+    let scale = 9 + exp - frac_digits;
+    let ns = if scale >= 0 {
+        mantissa.checked_mul(10u64.checked_pow(scale as u32)?)?
+    } else {
+        let divisor = 10u64.checked_pow((-scale) as u32)?;
+        if !mantissa.is_multiple_of(divisor) {
+            return None;
+        }
+        mantissa / divisor
+    };
+
+    (ns <= MAX_EXACT_NS).then_some(ns)
+}
+
+fn parse_size(text: &[u8]) -> Option<u32> {
+    if text.is_empty() || text.len() > 9 || !text.iter().all(u8::is_ascii_digit) {
+        // Leave signs and large values to the standard parser
+        return std::str::from_utf8(text).ok()?.parse().ok();
+    }
+
+    Some(
+        text.iter()
+            .fold(0, |size, &c| size * 10 + u32::from(c - b'0')),
+    )
+}
+
+/// Parses a trace line, without its line terminator.
+fn parse_line(
+    line: &[u8],
+    number: usize,
+    last: Option<Time>,
+) -> Result<Option<Packet>, TraceError> {
+    let content = match line.iter().position(|&c| c == b'#') {
+        Some(comment) => &line[..comment],
+        None => line,
+    };
+    let malformed = || TraceError::Malformed {
+        line: number,
+        text: String::from_utf8_lossy(line).trim_end().to_string(),
+    };
+    if !content.is_ascii() {
+        // Other whitespace than ASCII is unusual enough to go the slow way
+        let content = std::str::from_utf8(content).map_err(|_| malformed())?;
+        let fields: Vec<&str> = content.split_whitespace().collect();
+        return match fields[..] {
+            [] => Ok(None),
+            [time, size] => parse_fields(time.as_bytes(), size.as_bytes(), number, last),
+            _ => Err(malformed()),
+        };
+    }
+
+    let mut fields = content
+        .split(u8::is_ascii_whitespace)
+        .filter(|field| !field.is_empty());
+    match (fields.next(), fields.next(), fields.next()) {
+        (None, _, _) => Ok(None),
+        (Some(time), Some(size), None) => parse_fields(time, size, number, last),
+        _ => Err(malformed()),
+    }
+}
+
+fn parse_fields(
+    time: &[u8],
+    size: &[u8],
+    line: usize,
+    last: Option<Time>,
+) -> Result<Option<Packet>, TraceError> {
+    let arrival = parse_secs(time).ok_or_else(|| TraceError::InvalidTime {
+        line,
+        text: String::from_utf8_lossy(time).to_string(),
+    })?;
+    let size = parse_size(size).ok_or_else(|| TraceError::InvalidSize {
+        line,
+        text: String::from_utf8_lossy(size).to_string(),
+    })?;
+
+    match last {
+        Some(previous) if arrival < previous => Err(TraceError::Backwards {
+            line,
+            arrival,
+            previous,
+        }),
+        _ => Ok(Some(Packet::new(arrival, size))),
+    }
+}
+
+/// Reads a text trace with a packet per line: its arrival time in seconds
+/// and its size in bytes. Blank lines and everything after a `#` are
+/// ignored. After an error, reading goes on with the next line, except for
+/// I/O errors, which end the trace. Packets whose arrival goes backwards are
+/// reported and do not move the reference for the next ones.
+pub struct TraceReader<R: BufRead> {
+    input: R,
+    line: usize,
+    last: Option<Time>,
+    /// Reused for every line
+    text: Vec<u8>,
+    done: bool,
+}
+
+impl<R: BufRead> TraceReader<R> {
+    pub fn new(input: R) -> TraceReader<R> {
+        TraceReader {
+            input,
+            line: 0,
+            last: None,
+            text: Vec::new(),
+            done: false,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for TraceReader<R> {
+    type Item = Result<Packet, TraceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            self.text.clear();
+            self.line += 1;
+
+            match self.input.read_until(b'\n', &mut self.text) {
+                Ok(0) => self.done = true,
+                Ok(_) => match parse_line(&self.text, self.line, self.last) {
+                    Ok(None) => continue,
+                    Ok(Some(packet)) => {
+                        self.last = Some(packet.arrival());
+                        return Some(Ok(packet));
+                    }
+                    Err(err) => return Some(Err(err)),
+                },
+                Err(source) => {
+                    self.done = true;
+                    return Some(Err(TraceError::Io {
+                        line: self.line,
+                        source,
+                    }));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Reads a text trace, like `TraceReader`, straight from memory, such as a
+/// memory-mapped file. Lines are parsed in place, with no copies.
+pub struct TraceSliceReader<'a> {
+    data: &'a [u8],
+    line: usize,
+    last: Option<Time>,
+}
+
+impl<'a> TraceSliceReader<'a> {
+    pub fn new(data: &'a [u8]) -> TraceSliceReader<'a> {
+        TraceSliceReader {
+            data,
+            line: 0,
+            last: None,
+        }
+    }
+}
+
+impl Iterator for TraceSliceReader<'_> {
+    type Item = Result<Packet, TraceError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.data.is_empty() {
+            let end = self
+                .data
+                .iter()
+                .position(|&c| c == b'\n')
+                .map_or(self.data.len(), |newline| newline + 1);
+            let (line, rest) = self.data.split_at(end);
+            self.data = rest;
+            self.line += 1;
+
+            match parse_line(line, self.line, self.last) {
+                Ok(None) => continue,
+                Ok(Some(packet)) => {
+                    self.last = Some(packet.arrival());
+                    return Some(Ok(packet));
+                }
+                Err(err) => return Some(Err(err)),
+            }
+        }
+
+        None
+    }
+}
+
+/// Maps `file` into memory to read it with a `TraceSliceReader`.
+///
+/// # Safety
+///
+/// The file must not be modified while mapped.
+#[cfg(feature = "mmap")]
+pub unsafe fn map(file: &std::fs::File) -> io::Result<memmap2::Mmap> {
+    memmap2::Mmap::map(file)
+}
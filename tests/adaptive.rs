@@ -0,0 +1,44 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::phy::PhyProfile;
+use eee_hyst::policy::{AdaptivePolicy, SleepPolicy};
+use eee_hyst::simulator::Time;
+use eee_hyst::switch::Packet;
+
+#[test]
+fn hysteresis_follows_idle_periods() {
+    let mut policy = AdaptivePolicy::new(&PhyProfile::default(), Time(0), 0.5);
+
+    assert_eq!(policy.hysteresis(), Some(Time(7360)));
+    assert_eq!(policy.sleep_at(Time(1000)), Some(Time(8360)));
+
+    policy.idle_gap(Time(100_000));
+    assert_eq!(policy.estimate(), Some(Time(100_000)));
+    assert_eq!(policy.hysteresis(), Some(Time(0)));
+    assert_eq!(policy.sleep_at(Time(1000)), Some(Time(1000)));
+
+    for _ in 0..5 {
+        policy.idle_gap(Time(1000));
+    }
+    assert_eq!(policy.estimate(), Some(Time(4094)));
+    assert_eq!(policy.hysteresis(), Some(Time(7360)));
+}
+
+#[test]
+fn adaptive_simulation() {
+    let input = vec![(100, 1000), (6000, 1001), (500_000, 1000)];
+    let expected = vec![
+        Packet::new(Time(5380), 1000),
+        Packet::new(Time(6801), 1001),
+        Packet::new(Time(505_280), 1000),
+    ];
+
+    let policy = AdaptivePolicy::new(&PhyProfile::default(), Time(0), 0.125);
+    let mut sim = setup(&input, Time(0), Time(0)).with_policy(policy).tuples();
+
+    if let Err((i, packet)) = compare(&mut sim, expected.into_iter()) {
+        panic!("{}th packets yielded {:?}", i, packet);
+    }
+    assert_eq!(sim.policy().hysteresis(), Some(Time(0)));
+}
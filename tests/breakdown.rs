@@ -0,0 +1,78 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::simulator::{SimEvent, Time};
+use eee_hyst::stats::{BreakdownStats, DelayBreakdown};
+
+fn breakdowns(input: &[(u64, u32)], hyst: Time, idle: Time) -> Vec<DelayBreakdown> {
+    let mut stats = BreakdownStats::new();
+
+    setup(input, hyst, idle)
+        .filter_map(|ev| stats.record(&ev))
+        .collect()
+}
+
+fn breakdown(sleep: u64, idle: u64, wake: u64, queue: u64, transmission: u64) -> DelayBreakdown {
+    DelayBreakdown {
+        sleep: Time(sleep),
+        idle: Time(idle),
+        wake: Time(wake),
+        queue: Time(queue),
+        transmission: Time(transmission),
+    }
+}
+
+#[test]
+fn wake_and_queue() {
+    let input = vec![(1000, 1000), (2000, 64)];
+
+    assert_eq!(
+        breakdowns(&input, Time(0), Time(0)),
+        vec![
+            breakdown(0, 0, 4480, 0, 800),
+            breakdown(0, 0, 3480, 800, 51)
+        ]
+    );
+}
+
+#[test]
+fn idle_timer() {
+    let input = vec![(1000, 1000)];
+
+    assert_eq!(
+        breakdowns(&input, Time(0), Time(5000)),
+        vec![breakdown(0, 5000, 4480, 0, 800)]
+    );
+}
+
+#[test]
+fn arrival_while_entering_lpi() {
+    // The link starts entering LPI at 6280 and is in it from 9160
+    let input = vec![(1000, 1000), (7000, 1000)];
+
+    assert_eq!(
+        breakdowns(&input, Time(0), Time(0)),
+        vec![
+            breakdown(0, 0, 4480, 0, 800),
+            breakdown(2160, 0, 4480, 0, 800)
+        ]
+    );
+}
+
+#[test]
+fn parts_add_up_to_the_delay() {
+    let input: Vec<_> = (0..200u64)
+        .map(|i| (i * 3000 + i * i % 7 * 1000, 64 + i as u32 * 7))
+        .collect();
+    let mut stats = BreakdownStats::new();
+
+    for ev in setup(&input, Time(2000), Time(3000)) {
+        if let (Some(breakdown), SimEvent::Departure { time, packet }) = (stats.record(&ev), ev) {
+            assert_eq!(breakdown.total(), time - packet.arrival());
+        }
+    }
+
+    assert_eq!(stats.count(), input.len());
+    let shares = stats.shares().unwrap();
+    assert!((shares.iter().map(|(_, share)| share).sum::<f64>() - 100.0).abs() < 1e-9);
+}
@@ -0,0 +1,107 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::simulator::{SimEvent, Time};
+use eee_hyst::stats::QueueStats;
+use eee_hyst::switch::{BufferLimit, Packet};
+
+fn dropped(input: &[(u64, u32)], buffer: BufferLimit) -> (Vec<Packet>, Vec<Packet>) {
+    let mut departures = Vec::new();
+    let mut drops = Vec::new();
+
+    for ev in setup(input, Time(0), Time(5000)).with_buffer(buffer) {
+        match ev {
+            SimEvent::Departure { time, packet } => {
+                departures.push(Packet::new(time, packet.size()))
+            }
+            SimEvent::Drop { packet, .. } => drops.push(packet),
+            _ => {}
+        }
+    }
+
+    (departures, drops)
+}
+
+#[test]
+fn packet_limit() {
+    let input = vec![(100, 1000), (200, 1001), (300, 1002), (500_000, 1003)];
+
+    let (departures, drops) = dropped(&input, BufferLimit::new(Some(1), None));
+
+    assert_eq!(
+        departures,
+        vec![
+            Packet::new(Time(10_380), 1000),
+            Packet::new(Time(510_282), 1003)
+        ]
+    );
+    assert_eq!(
+        drops,
+        vec![Packet::new(Time(200), 1001), Packet::new(Time(300), 1002)]
+    );
+}
+
+#[test]
+fn byte_limit() {
+    let input = vec![(100, 1000), (200, 1000), (300, 1000)];
+
+    let (departures, drops) = dropped(&input, BufferLimit::new(None, Some(2500)));
+
+    assert_eq!(
+        departures,
+        vec![
+            Packet::new(Time(10_380), 1000),
+            Packet::new(Time(11_180), 1000)
+        ]
+    );
+    assert_eq!(drops, vec![Packet::new(Time(300), 1000)]);
+}
+
+#[test]
+fn transmitted_packet_frees_buffer() {
+    let input = vec![(100, 1000), (9700, 1000), (9800, 1000)];
+
+    let (departures, drops) = dropped(&input, BufferLimit::new(Some(1), None));
+
+    assert_eq!(departures.len(), 2);
+    assert_eq!(drops, vec![Packet::new(Time(9800), 1000)]);
+}
+
+#[test]
+fn arrivals_during_one_transmission() {
+    let input = vec![
+        (0, 1500),
+        (4490, 1500),
+        (4500, 1500),
+        (4510, 1500),
+        (4520, 1500),
+    ];
+    let mut drops = Vec::new();
+    let mut queue = QueueStats::new();
+
+    for ev in setup(&input, Time(0), Time(0)).with_buffer(BufferLimit::new(Some(2), None)) {
+        match ev {
+            SimEvent::Departure { time, packet } => queue.record(time, &packet),
+            SimEvent::Drop { packet, .. } => drops.push(packet),
+            _ => {}
+        }
+    }
+
+    // The first packet is transmitted from 4480 to 5680 while the rest arrive
+    assert_eq!(
+        drops,
+        vec![Packet::new(Time(4510), 1500), Packet::new(Time(4520), 1500)]
+    );
+    // Two waiting and the one being transmitted
+    assert_eq!(queue.max_packets(), 3);
+}
+
+#[test]
+fn unlimited_buffer() {
+    let input = vec![(100, 1000), (200, 1000), (300, 1000)];
+
+    let (departures, drops) = dropped(&input, BufferLimit::unlimited());
+
+    assert_eq!(departures.len(), 3);
+    assert!(drops.is_empty());
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::policy::{Coalescing, TimerPolicy};
+use eee_hyst::simulator::Time;
+use eee_hyst::switch::Packet;
+
+#[test]
+fn wake_on_packet_count() {
+    let input = vec![(100, 1000), (200, 1000), (300, 1000), (500_000, 1000)];
+    let expected = vec![
+        Packet::new(Time(5580), 1000),
+        Packet::new(Time(6380), 1000),
+        Packet::new(Time(7180), 1000),
+        Packet::new(Time(555_280), 1000),
+    ];
+
+    let mut sim = setup(&input, Time(0), Time(50_000))
+        .with_policy(Coalescing::new(
+            TimerPolicy::new(Time(0), Time(50_000)),
+            Some(3),
+            None,
+        ))
+        .tuples();
+
+    if let Err((i, packet)) = compare(&mut sim, expected.into_iter()) {
+        panic!("{}th packets yielded {:?}", i, packet);
+    }
+}
+
+#[test]
+fn wake_on_byte_count() {
+    let input = vec![(100, 1000), (200, 1000), (300, 1000)];
+    let expected = vec![
+        Packet::new(Time(5480), 1000),
+        Packet::new(Time(6280), 1000),
+        Packet::new(Time(7080), 1000),
+    ];
+
+    let mut sim = setup(&input, Time(0), Time(50_000))
+        .with_policy(Coalescing::new(
+            TimerPolicy::new(Time(0), Time(50_000)),
+            None,
+            Some(1500),
+        ))
+        .tuples();
+
+    if let Err((i, packet)) = compare(&mut sim, expected.into_iter()) {
+        panic!("{}th packets yielded {:?}", i, packet);
+    }
+}
+
+#[test]
+fn timer_expires_first() {
+    let input = vec![(100, 1000), (200, 1000), (10_000, 1000)];
+    let expected = vec![
+        Packet::new(Time(6380), 1000),
+        Packet::new(Time(7180), 1000),
+        Packet::new(Time(16_280), 1000),
+    ];
+
+    let mut sim = setup(&input, Time(0), Time(1000))
+        .with_policy(Coalescing::new(
+            TimerPolicy::new(Time(0), Time(1000)),
+            Some(3),
+            None,
+        ))
+        .tuples();
+
+    if let Err((i, packet)) = compare(&mut sim, expected.into_iter()) {
+        panic!("{}th packets yielded {:?}", i, packet);
+    }
+}
@@ -1,35 +1,53 @@
-use eee_hyst::simulator::{Simulator, Time};
-use eee_hyst::switch::{Packet, Status};
+#![allow(dead_code)]
+
+use eee_hyst::simulator::{Event, SimEvent, Simulator, Time};
+use eee_hyst::switch::Packet;
 use std::iter::Iterator;
 
+pub fn packets<'a>(input: &'a [(u64, u32)]) -> Box<dyn Iterator<Item = Packet> + 'a> {
+    Box::new(
+        input
+            .iter()
+            .map(|entry| Packet::new(Time(entry.0), entry.1)),
+    )
+}
+
 pub fn setup<'a>(
     input: &'a [(u64, u32)],
     hyst: Time,
     idle: Time,
 ) -> Simulator<Box<dyn Iterator<Item = Packet> + 'a>> {
-    let input_iter = Box::new(
-        input
-            .iter()
-            .map(|entry| Packet::new(Time(entry.0), entry.1)),
-    );
+    Simulator::new(hyst, idle, packets(input))
+}
+
+/// Events that may be a departure: `SimEvent`s and their tuple form.
+pub trait Departure {
+    /// The departed packet, with its departure time as arrival.
+    fn departure(&self) -> Option<Packet>;
+}
 
-    Simulator::new(hyst, idle, input_iter)
+impl Departure for SimEvent {
+    fn departure(&self) -> Option<Packet> {
+        match *self {
+            SimEvent::Departure { time, packet } => Some(Packet::new(time, packet.size())),
+            _ => None,
+        }
+    }
 }
 
-fn adapt_sim<'a, I: Iterator<Item = (Time, Option<Packet>, Option<Status>)>>(
+impl Departure for Event {
+    fn departure(&self) -> Option<Packet> {
+        self.1.map(|packet| Packet::new(self.0, packet.size()))
+    }
+}
+
+fn adapt_sim<'a, T: Departure, I: Iterator<Item = T>>(
     input: &'a mut I,
 ) -> Box<dyn 'a + Iterator<Item = Packet>> {
-    Box::new(
-        input
-            .filter(|ev| matches!(*ev, (_, Some(_), _)))
-            .map(|ev| Packet::new(ev.0, ev.1.unwrap().size())),
-    )
+    Box::new(input.filter_map(|ev| ev.departure()))
 }
 
-pub fn compare<
-    I: Iterator<Item = (Time, Option<Packet>, Option<Status>)>,
-    E: Iterator<Item = Packet>,
->(
+pub fn compare<T: Departure, I: Iterator<Item = T>, E: Iterator<Item = Packet>>(
     mut res: &mut I,
     expected: E,
 ) -> Result<u32, (usize, Packet)> {
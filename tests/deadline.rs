@@ -0,0 +1,48 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::phy::PhyProfile;
+use eee_hyst::policy::{DeadlinePolicy, SleepPolicy, Wake};
+use eee_hyst::simulator::{SimEvent, Time};
+use eee_hyst::switch::Packet;
+
+#[test]
+fn wakes_for_the_whole_queue() {
+    let mut policy = DeadlinePolicy::new(&PhyProfile::default(), Time(0), Time(20_000));
+    let mut queue = vec![Packet::new(Time(1000), 1000)];
+
+    // Tw and its own transmission
+    assert_eq!(policy.wake_at(&queue), Wake::By(Time(15_720)));
+
+    // The later packet is sent right after, so it does not bring the wake up forward
+    queue.push(Packet::new(Time(2000), 1000));
+    assert_eq!(policy.wake_at(&queue), Wake::By(Time(15_720)));
+
+    // A long packet right behind the first one does
+    queue.push(Packet::new(Time(2001), 9000));
+    assert_eq!(policy.wake_at(&queue), Wake::By(Time(8721)));
+
+    // Packets arriving after the wake up are not considered
+    queue.push(Packet::new(Time(10_000), 9000));
+    assert_eq!(policy.wake_at(&queue), Wake::By(Time(8721)));
+}
+
+#[test]
+fn bounded_delay() {
+    let mut input = vec![(100, 1000), (500_000, 1000)];
+    input.extend((0..30).map(|i| (1_000_000 + 100 * i, 1500)));
+    let policy = DeadlinePolicy::new(&PhyProfile::default(), Time(0), Time(50_000));
+
+    let delays: Vec<Time> = setup(&input, Time(0), Time(0))
+        .with_policy(policy)
+        .filter_map(|ev| match ev {
+            SimEvent::Departure { time, packet } => Some(time - packet.arrival()),
+            _ => None,
+        })
+        .collect();
+
+    assert_eq!(delays.len(), input.len());
+    assert_eq!(delays.iter().max(), Some(&Time(50_000)));
+    // Lone packets wait for as long as they can
+    assert_eq!(delays[1], Time(50_000));
+}
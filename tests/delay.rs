@@ -0,0 +1,59 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::simulator::Time;
+use eee_hyst::stats::DelayStats;
+
+#[test]
+fn delay_from_simulation() {
+    let input = vec![(100, 1000), (6000, 1001), (6100, 1000)];
+    let mut delays = DelayStats::new().with_samples();
+
+    for (time, packet, _) in setup(&input, Time(0), Time(0)).tuples() {
+        if let Some(packet) = packet {
+            delays.record(time, &packet);
+        }
+    }
+
+    assert_eq!(delays.count(), 3);
+    assert_eq!(delays.min(), Some(Time(5280)));
+    assert_eq!(delays.max(), Some(Time(8241)));
+    assert_eq!(delays.percentile(50.0), Some(Time(7541)));
+    assert_eq!(delays.percentile(99.9), Some(Time(8241)));
+    assert!((delays.mean().unwrap() - 7.020_666e-6).abs() < 1e-12);
+}
+
+#[test]
+fn delay_without_samples() {
+    let input = vec![(100, 1000), (6000, 1001), (6100, 1000)];
+    let mut delays = DelayStats::new();
+
+    for (time, packet, _) in setup(&input, Time(0), Time(0)).tuples() {
+        if let Some(packet) = packet {
+            delays.record(time, &packet);
+        }
+    }
+
+    assert_eq!(delays.count(), 3);
+    assert_eq!(delays.min(), Some(Time(5280)));
+    assert_eq!(delays.max(), Some(Time(8241)));
+    assert_eq!(delays.percentile(50.0), None);
+    assert!(delays.histogram(Time(1000)).is_empty());
+}
+
+#[test]
+fn delay_histogram() {
+    let input = vec![(100, 1000), (6000, 1001), (6100, 1000)];
+    let mut delays = DelayStats::new().with_samples();
+
+    for (time, packet, _) in setup(&input, Time(0), Time(0)).tuples() {
+        if let Some(packet) = packet {
+            delays.record(time, &packet);
+        }
+    }
+
+    assert_eq!(
+        delays.histogram(Time(1000)),
+        vec![(Time(5000), 1), (Time(7000), 1), (Time(8000), 1)]
+    );
+}
@@ -0,0 +1,103 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::simulator::{SimEvent, Time};
+use eee_hyst::switch::{BufferLimit, Packet, Status};
+
+fn state_change(time: u64, from: Status, to: Status) -> SimEvent {
+    SimEvent::StateChange {
+        time: Time(time),
+        from,
+        to,
+    }
+}
+
+#[test]
+fn events_in_order() {
+    let input = vec![(1000, 1000), (2000, 64), (100_000, 1000)];
+    let packets: Vec<_> = packets(&input).collect();
+    let events: Vec<_> = setup(&input, Time(0), Time(0)).collect();
+
+    assert_eq!(
+        events,
+        vec![
+            SimEvent::Arrival {
+                time: Time(1000),
+                packet: packets[0]
+            },
+            state_change(1000, Status::Off, Status::TOn),
+            SimEvent::Arrival {
+                time: Time(2000),
+                packet: packets[1]
+            },
+            state_change(5480, Status::TOn, Status::On),
+            SimEvent::Departure {
+                time: Time(6280),
+                packet: packets[0]
+            },
+            SimEvent::Departure {
+                time: Time(6331),
+                packet: packets[1]
+            },
+            state_change(6331, Status::On, Status::TOff),
+            state_change(9211, Status::TOff, Status::Off),
+            SimEvent::Arrival {
+                time: Time(100_000),
+                packet: packets[2]
+            },
+            state_change(100_000, Status::Off, Status::TOn),
+            state_change(104_480, Status::TOn, Status::On),
+            SimEvent::Departure {
+                time: Time(105_280),
+                packet: packets[2]
+            },
+        ]
+    );
+}
+
+#[test]
+fn every_packet_arrives_and_leaves_or_is_dropped() {
+    let input = vec![(100, 1000), (200, 1001), (300, 1002), (500_000, 1003)];
+    let events: Vec<_> = setup(&input, Time(0), Time(5000))
+        .with_buffer(BufferLimit::new(Some(1), None))
+        .collect();
+
+    let count = |f: fn(&SimEvent) -> bool| events.iter().filter(|ev| f(ev)).count();
+    let arrivals = count(|ev| matches!(ev, SimEvent::Arrival { .. }));
+    let departures = count(|ev| matches!(ev, SimEvent::Departure { .. }));
+    let drops = count(|ev| matches!(ev, SimEvent::Drop { .. }));
+
+    assert_eq!(arrivals, input.len());
+    assert_eq!(drops, 2);
+    assert_eq!(departures + drops, input.len());
+
+    // A drop always follows the arrival of its packet
+    for (i, ev) in events.iter().enumerate() {
+        if let SimEvent::Drop { packet, .. } = ev {
+            assert!(events[..i].contains(&SimEvent::Arrival {
+                time: packet.arrival(),
+                packet: *packet
+            }));
+        }
+    }
+}
+
+#[test]
+fn tuples_have_the_same_departures() {
+    let input = vec![(1000, 1000), (7000, 1001), (100_000, 64), (100_100, 1500)];
+
+    let departures: Vec<Packet> = setup(&input, Time(500), Time(2000))
+        .filter_map(|ev| match ev {
+            SimEvent::Departure { time, packet } => Some(Packet::new(time, packet.size())),
+            _ => None,
+        })
+        .collect();
+
+    let tuples: Vec<Packet> = setup(&input, Time(500), Time(2000))
+        .tuples()
+        .filter_map(|ev| ev.1.map(|packet| Packet::new(ev.0, packet.size())))
+        .collect();
+
+    assert_eq!(departures.len(), input.len());
+    assert_eq!(departures, tuples);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::phy::PhyProfile;
+use eee_hyst::simulator::Time;
+use eee_hyst::stats::GapStats;
+
+/// Ts + Tw = 3us, and a byte takes 1ns.
+fn gaps(input: &[(u64, u32)]) -> GapStats {
+    let phy = PhyProfile::new("test", Time(1000), Time(2000), Time(0), Time(0), 8e9);
+    let mut gaps = GapStats::new(phy);
+    for packet in packets(input) {
+        gaps.record(&packet);
+    }
+
+    gaps
+}
+
+#[test]
+fn gaps_between_transmissions() {
+    let mut gaps = gaps(&[
+        (100, 100),
+        (150, 100),
+        (1300, 100),
+        (5400, 100),
+        (5505, 100),
+    ]);
+
+    assert_eq!(gaps.packets(), 5);
+    assert_eq!(gaps.span(), Time(5505));
+    assert_eq!(gaps.busy(), Time(500));
+    assert_eq!(gaps.idle(), Time(5005));
+    assert_eq!(gaps.count(), 3);
+    assert_eq!(gaps.percentile(50.0), Some(Time(1000)));
+    assert_eq!(gaps.max(), Some(Time(4000)));
+    assert_eq!(
+        gaps.distribution(),
+        vec![(Time(0), 1, Time(5)), (Time(1000), 2, Time(5000))]
+    );
+}
+
+#[test]
+fn break_even() {
+    let gaps = gaps(&[(0, 100), (1100, 100), (5200, 100)]);
+
+    assert_eq!(gaps.break_even(), Time(3000));
+    assert_eq!(gaps.over_break_even().collect::<Vec<_>>(), vec![Time(4000)]);
+    assert_eq!(gaps.over_break_even_pct(), Some(80.0));
+    assert_eq!(gaps.best_lpi_pct(), Some(100.0 * 1000.0 / 5300.0));
+}
+
+#[test]
+fn no_gaps() {
+    let mut gaps = gaps(&[(0, 100), (100, 100)]);
+
+    assert_eq!(gaps.count(), 0);
+    assert_eq!(gaps.mean(), None);
+    assert_eq!(gaps.percentile(50.0), None);
+    assert_eq!(gaps.over_break_even_pct(), None);
+    assert_eq!(gaps.best_lpi_pct(), Some(0.0));
+}
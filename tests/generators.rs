@@ -0,0 +1,111 @@
+use eee_hyst::generators::{rate_for_load, Cbr, Mmpp, OnOff, PacketSize, Poisson};
+use eee_hyst::simulator::{Simulator, Time};
+use eee_hyst::switch::Packet;
+
+fn offered_load(packets: &[Packet], duration: Time, capacity: f64) -> f64 {
+    let bits: u64 = packets.iter().map(|p| 8 * u64::from(p.size())).sum();
+
+    bits as f64 / duration.as_secs() / capacity
+}
+
+fn first(source: impl Iterator<Item = Packet>, end: Time) -> Vec<Packet> {
+    source.take_while(|p| p.arrival() < end).collect()
+}
+
+#[test]
+fn packet_sizes() {
+    assert_eq!("1500".parse(), Ok(PacketSize::Fixed(1500)));
+    assert_eq!("IMIX".parse(), Ok(PacketSize::Imix));
+    assert!("0".parse::<PacketSize>().is_err());
+    assert!((PacketSize::Imix.mean() - 4252.0 / 12.0).abs() < 1e-9);
+
+    let sizes: Vec<_> = Cbr::new(1e6, PacketSize::Imix, 3)
+        .take(12_000)
+        .map(|p| p.size())
+        .collect();
+    let small = sizes.iter().filter(|size| **size == 64).count();
+    assert!(sizes.iter().all(|size| [64, 576, 1500].contains(size)));
+    assert!((6500..7500).contains(&small));
+}
+
+#[test]
+fn constant_bit_rate() {
+    let rate = rate_for_load(0.5, 10e9, PacketSize::Fixed(1250));
+
+    assert_eq!(rate, 500_000.0);
+    assert_eq!(
+        Cbr::new(rate, PacketSize::Fixed(1250), 0)
+            .take(3)
+            .collect::<Vec<_>>(),
+        vec![
+            Packet::new(Time(0), 1250),
+            Packet::new(Time(2000), 1250),
+            Packet::new(Time(4000), 1250)
+        ]
+    );
+}
+
+#[test]
+fn reproducible() {
+    let size = PacketSize::Imix;
+    let end = Time(1_000_000);
+
+    assert_eq!(
+        first(Poisson::new(1e6, size, 7), end),
+        first(Poisson::new(1e6, size, 7), end)
+    );
+    assert_ne!(
+        first(Poisson::new(1e6, size, 7), end),
+        first(Poisson::new(1e6, size, 8), end)
+    );
+    assert_eq!(
+        first(OnOff::new(1e6, 4e6, Time(10_000), 1.5, size, 7), end),
+        first(OnOff::new(1e6, 4e6, Time(10_000), 1.5, size, 7), end)
+    );
+    assert_eq!(
+        first(Mmpp::new([2e6, 0.5e6], [Time(10_000); 2], size, 7), end),
+        first(Mmpp::new([2e6, 0.5e6], [Time(10_000); 2], size, 7), end)
+    );
+}
+
+#[test]
+fn target_load() {
+    let size = PacketSize::Fixed(1000);
+    let rate = rate_for_load(0.2, 10e9, size);
+    let end = Time(100_000_000);
+
+    let poisson = first(Poisson::new(rate, size, 1), end);
+    assert!((offered_load(&poisson, end, 10e9) - 0.2).abs() < 0.005);
+
+    let rates = [2.0 * rate * 4.0 / 5.0, 2.0 * rate / 5.0];
+    assert!((Mmpp::mean_rate(rates, [Time(100_000); 2]) - rate).abs() < 1e-6);
+    let mmpp = first(Mmpp::new(rates, [Time(100_000); 2], size, 1), end);
+    assert!((offered_load(&mmpp, end, 10e9) - 0.2).abs() < 0.01);
+}
+
+#[test]
+fn bursts_at_peak_rate() {
+    let packets = first(
+        OnOff::new(1e5, 1e6, Time(50_000), 2.5, PacketSize::Fixed(100), 5),
+        Time(10_000_000),
+    );
+    let gaps: Vec<_> = packets
+        .windows(2)
+        .map(|w| w[1].arrival() - w[0].arrival())
+        .collect();
+
+    assert!(gaps.iter().all(|gap| *gap >= Time(1000)));
+    assert!(gaps.iter().filter(|gap| **gap == Time(1000)).count() > gaps.len() / 2);
+    assert!(gaps.iter().any(|gap| *gap > Time(50_000)));
+}
+
+#[test]
+fn feeds_the_simulator() {
+    let input = Poisson::new(1e6, PacketSize::Imix, 0).take(1000);
+    let departures = Simulator::new(Time(0), Time(0), input)
+        .tuples()
+        .filter(|ev| ev.1.is_some())
+        .count();
+
+    assert_eq!(departures, 1000);
+}
@@ -0,0 +1,112 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::phy::PhyProfile;
+use eee_hyst::policy::{OraclePolicy, SleepPolicy, Wake};
+use eee_hyst::power::PowerModel;
+use eee_hyst::simulator::{SimEvent, Time};
+use eee_hyst::stats::{WakeStats, WakeUp};
+use eee_hyst::switch::{Packet, Status};
+
+fn oracle(input: &[(u64, u32)], power: &PowerModel) -> OraclePolicy {
+    OraclePolicy::new(
+        &PhyProfile::default(),
+        power,
+        packets(input).map(|packet| packet.arrival()),
+    )
+}
+
+#[test]
+fn sleeps_in_long_gaps() {
+    let power = PowerModel::from_phy(&PhyProfile::default());
+    let mut policy = oracle(&[(100, 1000), (10_000, 1000), (20_000, 1000)], &power);
+
+    assert_eq!(policy.min_gap(), Some(Time(7360)));
+    assert_eq!(policy.sleep_at(Time(900)), Some(Time(900)));
+    assert_eq!(policy.sleep_at(Time(13_000)), None);
+    // Nothing else arrives
+    assert_eq!(policy.sleep_at(Time(20_800)), Some(Time(20_800)));
+    assert_eq!(
+        policy.wake_at(&[Packet::new(Time(20_000), 1000)]),
+        Wake::At(Time(15_520))
+    );
+}
+
+#[test]
+fn costly_transitions() {
+    // Waking up costs as much as another 1.8 Tw in LPI
+    let power = PowerModel::new(1.0, 0.2, 2.0, 1.0);
+    assert_eq!(oracle(&[], &power).min_gap(), Some(Time(12_960)));
+
+    let power = PowerModel::new(1.0, 1.0, 1.0, 1.0);
+    assert_eq!(oracle(&[], &power).min_gap(), None);
+}
+
+#[test]
+fn oracle_adds_no_delay() {
+    let input = vec![(100, 1000), (3000, 1000), (100_000, 1000), (104_000, 1000)];
+    let power = PowerModel::from_phy(&PhyProfile::default());
+    let events: Vec<SimEvent> = setup(&input, Time(0), Time(0))
+        .with_policy(oracle(&input, &power))
+        .collect();
+
+    let delays: Vec<Time> = events
+        .iter()
+        .filter_map(|ev| match ev {
+            SimEvent::Departure { time, packet } => Some(*time - packet.arrival()),
+            _ => None,
+        })
+        .collect();
+    let states: Vec<(Time, Status)> = events
+        .iter()
+        .filter_map(|ev| match ev {
+            SimEvent::StateChange { time, to, .. } => Some((*time, *to)),
+            _ => None,
+        })
+        .collect();
+
+    // The first two packets wait for the link to wake up from the start,
+    // the second one also for the first. Later packets only take their
+    // transmission time, and the link stays on through the last, short, gap
+    assert_eq!(delays, vec![Time(5180), Time(3080), Time(800), Time(800)]);
+    assert_eq!(
+        states,
+        vec![
+            (Time(0), Status::TOn),
+            (Time(4480), Status::On),
+            (Time(6080), Status::TOff),
+            (Time(8960), Status::Off),
+            (Time(95_520), Status::TOn),
+            (Time(100_000), Status::On),
+        ]
+    );
+}
+
+#[test]
+fn early_wake_ups_have_a_cause() {
+    let input = vec![(100, 1000), (3000, 1000), (100_000, 1000), (104_000, 1000)];
+    let packets: Vec<Packet> = packets(&input).collect();
+    let power = PowerModel::from_phy(&PhyProfile::default());
+    let mut wakes = WakeStats::new();
+
+    // The oracle wakes up before the packets arrive
+    for ev in setup(&input, Time(0), Time(0)).with_policy(oracle(&input, &power)) {
+        wakes.record(&ev);
+    }
+
+    assert_eq!(
+        wakes.wakes(),
+        &[
+            WakeUp {
+                time: Time(0),
+                cause: packets[0],
+                packets: 2,
+            },
+            WakeUp {
+                time: Time(95_520),
+                cause: packets[2],
+                packets: 2,
+            },
+        ]
+    );
+}
@@ -0,0 +1,69 @@
+use eee_hyst::output::{Format, RecordWriter, Value};
+use eee_hyst::simulator::Time;
+
+fn write(format: Format, records: &[Vec<(&str, Value)>]) -> String {
+    let mut writer = RecordWriter::new(Vec::new(), format);
+    for record in records {
+        writer.write(record).unwrap();
+    }
+
+    String::from_utf8(writer.finish().unwrap()).unwrap()
+}
+
+fn departures() -> Vec<Vec<(&'static str, Value)>> {
+    vec![
+        vec![("time", Time(5280).into()), ("size", 1000u32.into())],
+        vec![("time", Time(13_441).into()), ("size", 1001u32.into())],
+    ]
+}
+
+#[test]
+fn text() {
+    assert_eq!(
+        write(Format::Text, &departures()),
+        "5.28e-6\t1000\n1.3441e-5\t1001\n"
+    );
+}
+
+#[test]
+fn csv() {
+    assert_eq!(
+        write(Format::Csv, &departures()),
+        "time,size\n5.28e-6,1000\n1.3441e-5,1001\n"
+    );
+    assert_eq!(
+        write(
+            Format::Csv,
+            &[vec![("name", "a,\"b\"".into()), ("value", Value::Null)]]
+        ),
+        "name,value\n\"a,\"\"b\"\"\",\n"
+    );
+}
+
+#[test]
+fn json() {
+    assert_eq!(
+        write(Format::Json, &departures()),
+        "[\n{\"time\":5.28e-6,\"size\":1000},\n{\"time\":1.3441e-5,\"size\":1001}\n]\n"
+    );
+    assert_eq!(write(Format::Json, &[]), "[]\n");
+    assert_eq!(
+        write(
+            Format::Json,
+            &[vec![
+                ("state", "T_\"ON\"\n".into()),
+                ("power", f64::NAN.into()),
+                ("delay", None::<Time>.into())
+            ]]
+        ),
+        "[\n{\"state\":\"T_\\\"ON\\\"\\n\",\"power\":null,\"delay\":null}\n]\n"
+    );
+}
+
+#[test]
+fn jsonl() {
+    assert_eq!(
+        write(Format::Jsonl, &departures()),
+        "{\"time\":5.28e-6,\"size\":1000}\n{\"time\":1.3441e-5,\"size\":1001}\n"
+    );
+}
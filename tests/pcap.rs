@@ -0,0 +1,269 @@
+use eee_hyst::pcap::{is_capture, PcapReader, PcapWriter};
+use eee_hyst::simulator::Time;
+use eee_hyst::switch::Packet;
+use eee_hyst::trace::TraceError;
+
+fn pcap(magic: u32, big_endian: bool, records: &[(u32, u32, u32)]) -> Vec<u8> {
+    let u32_bytes = |v: u32| {
+        if big_endian {
+            v.to_be_bytes()
+        } else {
+            v.to_le_bytes()
+        }
+    };
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(&u32_bytes(magic));
+    bytes.extend_from_slice(&[0; 16]);
+    bytes.extend_from_slice(&u32_bytes(1));
+    for (sec, frac, len) in records {
+        for field in [*sec, *frac, 60, *len] {
+            bytes.extend_from_slice(&u32_bytes(field));
+        }
+        bytes.extend_from_slice(&[0; 60]);
+    }
+
+    bytes
+}
+
+fn pcapng_block(kind: u32, body: &[u8]) -> Vec<u8> {
+    let len = (12 + body.len()) as u32;
+    let mut bytes = Vec::new();
+
+    bytes.extend_from_slice(&kind.to_le_bytes());
+    bytes.extend_from_slice(&len.to_le_bytes());
+    bytes.extend_from_slice(body);
+    bytes.extend_from_slice(&len.to_le_bytes());
+
+    bytes
+}
+
+fn pcapng(tsresol: Option<u8>, records: &[(u64, u32)]) -> Vec<u8> {
+    let mut shb = Vec::new();
+    shb.extend_from_slice(&0x1a2b_3c4du32.to_le_bytes());
+    shb.extend_from_slice(&[1, 0, 0, 0]);
+    shb.extend_from_slice(&(-1i64).to_le_bytes());
+
+    let mut idb = vec![1, 0, 0, 0, 0xff, 0xff, 0, 0];
+    if let Some(tsresol) = tsresol {
+        idb.extend_from_slice(&[9, 0, 1, 0, tsresol, 0, 0, 0]);
+    }
+
+    let mut bytes = pcapng_block(0x0a0d_0d0a, &shb);
+    bytes.extend(pcapng_block(1, &idb));
+    // A name resolution block, which carries no packets
+    bytes.extend(pcapng_block(4, &[0, 0, 0, 0]));
+    for (timestamp, len) in records {
+        let mut epb = Vec::new();
+        for field in [0, (timestamp >> 32) as u32, *timestamp as u32, 0, *len] {
+            epb.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend(pcapng_block(6, &epb));
+    }
+
+    bytes
+}
+
+fn read(bytes: &[u8]) -> Vec<Packet> {
+    PcapReader::new(bytes)
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap()
+}
+
+#[test]
+fn pcap_micros() {
+    let bytes = pcap(0xa1b2_c3d4, false, &[(10, 999_999, 1000), (11, 5, 1514)]);
+
+    assert!(is_capture(&bytes));
+    assert_eq!(
+        read(&bytes),
+        vec![Packet::new(Time(0), 1000), Packet::new(Time(6000), 1514)]
+    );
+}
+
+#[test]
+fn pcap_nanos_big_endian() {
+    let bytes = pcap(0xa1b2_3c4d, true, &[(10, 100, 64), (10, 4580, 128)]);
+
+    assert!(is_capture(&bytes));
+    assert_eq!(
+        read(&bytes),
+        vec![Packet::new(Time(0), 64), Packet::new(Time(4480), 128)]
+    );
+}
+
+#[test]
+fn pcapng_default_resolution() {
+    let bytes = pcapng(None, &[(1_000_000, 1000), (1_000_006, 1001)]);
+
+    assert!(is_capture(&bytes));
+    assert_eq!(
+        read(&bytes),
+        vec![Packet::new(Time(0), 1000), Packet::new(Time(6000), 1001)]
+    );
+}
+
+#[test]
+fn pcapng_nanosecond_resolution() {
+    let bytes = pcapng(Some(9), &[(1 << 40, 60), ((1 << 40) + 2880, 60)]);
+
+    assert_eq!(
+        read(&bytes),
+        vec![Packet::new(Time(0), 60), Packet::new(Time(2880), 60)]
+    );
+}
+
+#[test]
+fn truncated_capture() {
+    let bytes = pcap(0xa1b2_c3d4, false, &[(10, 0, 1000), (10, 6, 1001)]);
+    let packets: Vec<_> = PcapReader::new(&bytes[..bytes.len() - 10])
+        .unwrap()
+        .collect();
+
+    assert_eq!(packets.len(), 2);
+    assert!(packets[0].is_ok());
+    assert!(packets[1].is_err());
+}
+
+#[test]
+fn huge_pcapng_blocks() {
+    let mut bytes = pcapng(None, &[(1_000_000, 1000)]);
+    let short = bytes.len();
+    // A packet block claiming almost 4 GB, cut short, and an interface
+    // description block too long to be real
+    bytes.extend_from_slice(&6u32.to_le_bytes());
+    bytes.extend_from_slice(&0xffff_fffcu32.to_le_bytes());
+    bytes.extend_from_slice(&[0; 64]);
+    let packets: Vec<_> = PcapReader::new(&bytes[..]).unwrap().collect();
+
+    assert_eq!(packets.len(), 2);
+    assert!(packets[0].is_ok());
+    assert!(packets[1].is_err());
+
+    bytes.truncate(short);
+    bytes.extend(pcapng_block(1, &vec![0; 1 << 16]));
+    let packets: Vec<_> = PcapReader::new(&bytes[..]).unwrap().collect();
+
+    assert_eq!(packets.len(), 2);
+    assert!(packets[1].is_err());
+}
+
+#[test]
+fn text_is_not_capture() {
+    assert!(!is_capture(b"1e-7\t1000\n"));
+    assert!(PcapReader::new(&b"1e-7\t1000\n"[..]).is_err());
+}
+
+#[test]
+fn write_departures() {
+    let mut writer = PcapWriter::new(Vec::new(), false).unwrap();
+    writer
+        .write(Time(2_000_000_005), &Packet::new(Time(0), 1000))
+        .unwrap();
+    writer
+        .write(Time(2_000_006_005), &Packet::new(Time(0), 1514))
+        .unwrap();
+    let bytes = writer.into_inner().unwrap();
+
+    assert_eq!(bytes.len(), 24 + 2 * 16);
+    assert_eq!(&bytes[24..28], &2u32.to_le_bytes());
+    assert_eq!(&bytes[28..32], &5u32.to_le_bytes());
+    assert_eq!(
+        read(&bytes),
+        vec![Packet::new(Time(0), 1000), Packet::new(Time(6000), 1514)]
+    );
+}
+
+#[test]
+fn write_dummy_payload() {
+    let mut writer = PcapWriter::new(Vec::new(), true).unwrap();
+    writer
+        .write(Time(5280), &Packet::new(Time(0), 100))
+        .unwrap();
+    let bytes = writer.into_inner().unwrap();
+
+    assert_eq!(bytes.len(), 24 + 16 + 100);
+    assert_eq!(&bytes[32..36], &100u32.to_le_bytes());
+    assert_eq!(read(&bytes), vec![Packet::new(Time(0), 100)]);
+}
+
+fn ipv4_udp(src: u8, sport: u16, len: usize) -> Vec<u8> {
+    let mut frame = vec![0; 12];
+    frame.extend_from_slice(&[0x08, 0x00]);
+    frame.extend_from_slice(&[0x45, 0, 0, 0, 0, 0, 0, 0, 64, 17, 0, 0]);
+    frame.extend_from_slice(&[10, 0, 0, src, 10, 0, 0, 1]);
+    frame.extend_from_slice(&sport.to_be_bytes());
+    frame.extend_from_slice(&53u16.to_be_bytes());
+    frame.resize(len, 0);
+
+    frame
+}
+
+#[test]
+fn pcap_flows() {
+    let vlan = {
+        let frame = ipv4_udp(2, 1000, 60);
+        let mut tagged = frame[..12].to_vec();
+        tagged.extend_from_slice(&[0x81, 0x00, 0, 5]);
+        tagged.extend_from_slice(&frame[12..]);
+        tagged
+    };
+    let mut arp = vec![0; 60];
+    arp[12..14].copy_from_slice(&[0x08, 0x06]);
+    let frames = [
+        ipv4_udp(2, 1000, 60),
+        ipv4_udp(3, 1000, 60),
+        arp,
+        vlan,
+        ipv4_udp(2, 1001, 60),
+        // Cut before the ports
+        ipv4_udp(2, 1000, 36),
+    ];
+
+    let mut bytes = pcap(0xa1b2_c3d4, false, &[]);
+    for (i, frame) in frames.iter().enumerate() {
+        for field in [0, i as u32, frame.len() as u32, 1000] {
+            bytes.extend_from_slice(&field.to_le_bytes());
+        }
+        bytes.extend_from_slice(frame);
+    }
+
+    let flows: Vec<_> = read(&bytes).iter().map(Packet::flow).collect();
+    assert_eq!(
+        flows,
+        vec![Some(0), Some(1), None, Some(0), Some(2), Some(3)]
+    );
+}
+
+#[test]
+fn backwards_timestamps() {
+    let bytes = pcap(
+        0xa1b2_c3d4,
+        false,
+        &[
+            (10, 5, 1000),
+            (10, 9, 1001),
+            (10, 7, 1002),
+            (10, 1, 1003),
+            (10, 12, 1004),
+        ],
+    );
+    let packets: Vec<_> = PcapReader::new(&bytes[..]).unwrap().collect();
+
+    assert_eq!(packets.len(), 5);
+    assert!(matches!(
+        packets[2],
+        Err(TraceError::Backwards {
+            line: 3,
+            arrival: Time(2000),
+            previous: Time(4000),
+        })
+    ));
+    // Before the first packet
+    assert!(matches!(
+        packets[3],
+        Err(TraceError::Backwards { line: 4, .. })
+    ));
+    assert_eq!(packets[4].as_ref().unwrap(), &Packet::new(Time(7000), 1004));
+}
@@ -0,0 +1,50 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::phy::PhyProfile;
+use eee_hyst::simulator::{Simulator, Time};
+use eee_hyst::switch::Packet;
+
+#[test]
+fn gigabit_to_off_and_back() {
+    let input = vec![(100, 1000), (100_000, 1001)];
+    let expected = vec![
+        Packet::new(Time(24_600), 1000),
+        Packet::new(Time(231_108), 1001),
+    ];
+
+    let phy: PhyProfile = "1000base-t".parse().unwrap();
+    let mut sim = Simulator::new_explicit(Time(0), Time(0), packets(&input), phy).tuples();
+
+    if let Err((i, packet)) = compare(&mut sim, expected.into_iter()) {
+        panic!("{}th packets yielded {:?}", i, packet);
+    }
+}
+
+#[test]
+fn gigabit_with_ts_override() {
+    let input = vec![(100, 1000), (100_000, 1001)];
+    let expected = vec![
+        Packet::new(Time(24_600), 1000),
+        Packet::new(Time(124_508), 1001),
+    ];
+
+    let phy = PhyProfile::by_name("1000BASE-T")
+        .unwrap()
+        .with_ts(Time(10_000));
+    let mut sim = Simulator::new_explicit(Time(0), Time(0), packets(&input), phy).tuples();
+
+    if let Err((i, packet)) = compare(&mut sim, expected.into_iter()) {
+        panic!("{}th packets yielded {:?}", i, packet);
+    }
+}
+
+#[test]
+fn default_profile_is_10gbase_t() {
+    let phy = PhyProfile::default();
+
+    assert_eq!(phy.name(), "10gbase-t");
+    assert_eq!(phy.t_s(), Time(2880));
+    assert_eq!(phy.t_w(), Time(4480));
+    assert!("20gbase-t".parse::<PhyProfile>().is_err());
+}
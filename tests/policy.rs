@@ -0,0 +1,61 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::policy::{SleepPolicy, TimerPolicy, Wake};
+use eee_hyst::simulator::Time;
+use eee_hyst::switch::{Packet, Status};
+
+/// Never enters LPI once the link is up.
+struct NeverSleep;
+
+impl SleepPolicy for NeverSleep {
+    fn sleep_at(&mut self, _idle_since: Time) -> Option<Time> {
+        None
+    }
+
+    fn wake_at(&mut self, queue: &[Packet]) -> Wake {
+        Wake::At(queue[0].arrival())
+    }
+}
+
+#[test]
+fn timer_policy_is_default() {
+    let input = vec![(100, 1000), (6000, 1001)];
+    let expected = vec![
+        Packet::new(Time(5880), 1000),
+        Packet::new(Time(14_041), 1001),
+    ];
+
+    let mut sim = setup(&input, Time(0), Time(0))
+        .with_policy(TimerPolicy::new(Time(0), Time(500)))
+        .tuples();
+
+    if let Err((i, packet)) = compare(&mut sim, expected.into_iter()) {
+        panic!("{}th packets yielded {:?}", i, packet);
+    }
+}
+
+#[test]
+fn custom_policy() {
+    let input = vec![(100, 1000), (6000, 1001), (1_000_000, 1002)];
+    let expected = vec![
+        Packet::new(Time(5380), 1000),
+        Packet::new(Time(6801), 1001),
+        Packet::new(Time(1_000_802), 1002),
+    ];
+
+    let mut sim = setup(&input, Time(0), Time(0))
+        .with_policy(NeverSleep)
+        .tuples();
+    let states: Vec<Status> = setup(&input, Time(0), Time(0))
+        .with_policy(NeverSleep)
+        .tuples()
+        .filter_map(|ev| ev.2)
+        .filter(|state| *state != Status::On)
+        .collect();
+
+    if let Err((i, packet)) = compare(&mut sim, expected.into_iter()) {
+        panic!("{}th packets yielded {:?}", i, packet);
+    }
+    assert_eq!(states, vec![Status::TOn]);
+}
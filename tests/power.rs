@@ -0,0 +1,39 @@
+use eee_hyst::phy::PhyProfile;
+use eee_hyst::power::{Power, PowerModel};
+use eee_hyst::simulator::Time;
+use eee_hyst::switch::Status;
+
+#[test]
+fn parse_power() {
+    assert_eq!("0.4".parse::<Power>(), Ok(Power::Watts(0.4)));
+    assert_eq!("2.5W".parse::<Power>(), Ok(Power::Watts(2.5)));
+    assert_eq!("10%".parse::<Power>(), Ok(Power::Fraction(0.1)));
+    assert!("lots".parse::<Power>().is_err());
+    assert!("-3W".parse::<Power>().is_err());
+    assert!("-10%".parse::<Power>().is_err());
+    assert!("nanW".parse::<Power>().is_err());
+    assert!("inf%".parse::<Power>().is_err());
+    assert!("infinity".parse::<Power>().is_err());
+}
+
+#[test]
+fn profile_power() {
+    let model = PowerModel::from_phy(&PhyProfile::default());
+
+    assert_eq!(model.power(Status::On), 4.0);
+    assert_eq!(model.power(Status::Off), 0.4);
+    assert_eq!(model.power(Status::TOn), 4.0);
+    assert_eq!(model.power(Status::TOff), 4.0);
+}
+
+#[test]
+fn overridden_power() {
+    let model = PowerModel::new(2.0, 0.2, 2.0, 2.0)
+        .with_power(Status::Off, Power::Fraction(0.05))
+        .with_power(Status::TOn, Power::Watts(3.0));
+
+    assert_eq!(model.power(Status::Off), 0.1);
+    assert_eq!(model.power(Status::TOn), 3.0);
+    assert_eq!(model.energy(Status::TOn, Time(2_000_000_000)), 6.0);
+    assert_eq!(model.always_on_energy(Time(500_000_000)), 1.0);
+}
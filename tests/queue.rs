@@ -0,0 +1,72 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::simulator::Time;
+use eee_hyst::stats::{QueueSample, QueueStats};
+use eee_hyst::switch::Packet;
+
+fn sample(time: u64, packets: usize, bytes: u64) -> QueueSample {
+    QueueSample {
+        time: Time(time),
+        packets,
+        bytes,
+    }
+}
+
+#[test]
+fn backlog_during_lpi() {
+    let input = vec![(1000, 1000), (7000, 1001), (100_000, 64)];
+    let mut queue = QueueStats::new().with_series();
+
+    for ev in setup(&input, Time(0), Time(5000)).tuples() {
+        if let (time, Some(packet), _) = ev {
+            queue.record(time, &packet);
+        }
+    }
+    queue.finish(Time(109_531));
+
+    assert_eq!(
+        queue.series().unwrap(),
+        &[
+            sample(1000, 1, 1000),
+            sample(7000, 2, 2001),
+            sample(11_280, 1, 1001),
+            sample(12_081, 0, 0),
+            sample(100_000, 1, 64),
+            sample(109_531, 0, 0)
+        ]
+    );
+    assert_eq!(queue.max_packets(), 2);
+    assert_eq!(queue.max_bytes(), 2001);
+
+    let busy = 6000 + 2 * 4280 + 801 + 9531;
+    let mean = queue.mean_packets().unwrap();
+    assert!((mean - busy as f64 / 109_531.0).abs() < 1e-12);
+
+    let dist = queue.distribution();
+    assert_eq!(dist.len(), 3);
+    assert!((dist[2].1 - 4280.0 / 109_531.0).abs() < 1e-12);
+    assert!((dist.iter().map(|(_, share)| share).sum::<f64>() - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn departure_before_next_arrival() {
+    let mut queue = QueueStats::new();
+
+    queue.record(Time(300), &Packet::new(Time(100), 500));
+    queue.record(Time(500), &Packet::new(Time(300), 500));
+    queue.finish(Time(1000));
+
+    assert_eq!(queue.max_packets(), 1);
+    assert_eq!(queue.mean_bytes(), Some(200.0));
+    assert!(queue.series().is_none());
+}
+
+#[test]
+fn empty() {
+    let mut queue = QueueStats::new();
+    queue.finish(Time(0));
+
+    assert_eq!(queue.mean_packets(), None);
+    assert_eq!(queue.max_packets(), 0);
+}
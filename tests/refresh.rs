@@ -0,0 +1,96 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::phy::PhyProfile;
+use eee_hyst::simulator::{Simulator, Time};
+use eee_hyst::switch::{Packet, Refresh, Status};
+
+fn states(input: &[(u64, u32)], refresh: Refresh) -> Vec<(Time, Status)> {
+    setup(input, Time(0), Time(0))
+        .with_refresh(refresh)
+        .tuples()
+        .filter_map(|ev| ev.2.map(|state| (ev.0, state)))
+        .collect()
+}
+
+#[test]
+fn refresh_cycles() {
+    let input = vec![(100, 1000), (100_000, 1001)];
+
+    let lpi: Vec<(Time, Status)> = states(&input, Refresh::Interruptible)
+        .into_iter()
+        .skip_while(|(time, _)| *time < Time(8260))
+        .take(5)
+        .collect();
+
+    assert_eq!(
+        lpi,
+        vec![
+            (Time(8260), Status::Off),
+            (Time(47_940), Status::Refresh),
+            (Time(49_220), Status::Off),
+            (Time(88_900), Status::Refresh),
+            (Time(90_180), Status::Off),
+        ]
+    );
+}
+
+#[test]
+fn wake_interrupts_refresh() {
+    let input = vec![(100, 1000), (48_000, 1001)];
+    let expected = vec![
+        Packet::new(Time(5380), 1000),
+        Packet::new(Time(53_281), 1001),
+    ];
+
+    let mut sim = setup(&input, Time(0), Time(0))
+        .with_refresh(Refresh::Interruptible)
+        .tuples();
+
+    if let Err((i, packet)) = compare(&mut sim, expected.into_iter()) {
+        panic!("{}th packets yielded {:?}", i, packet);
+    }
+    assert!(states(&input, Refresh::Interruptible).contains(&(Time(48_000), Status::TOn)));
+}
+
+#[test]
+fn wake_waits_for_refresh() {
+    let input = vec![(100, 1000), (48_000, 1001)];
+    let expected = vec![
+        Packet::new(Time(5380), 1000),
+        Packet::new(Time(54_501), 1001),
+    ];
+
+    let mut sim = setup(&input, Time(0), Time(0))
+        .with_refresh(Refresh::Blocking)
+        .tuples();
+
+    if let Err((i, packet)) = compare(&mut sim, expected.into_iter()) {
+        panic!("{}th packets yielded {:?}", i, packet);
+    }
+    assert!(states(&input, Refresh::Blocking).contains(&(Time(49_220), Status::TOn)));
+}
+
+#[test]
+fn disabled_refresh() {
+    let input = vec![(100, 1000), (100_000, 1001)];
+
+    assert!(!states(&input, Refresh::Disabled)
+        .iter()
+        .any(|(_, state)| *state == Status::Refresh));
+}
+
+#[test]
+fn no_refresh_cycles() {
+    let input = vec![(100, 1000), (100_000, 1001)];
+    let phy = PhyProfile::new("test", Time(2880), Time(4480), Time(0), Time(0), 10e9);
+
+    let states: Vec<Status> = Simulator::new_explicit(Time(0), Time(0), packets(&input), phy)
+        .with_refresh(Refresh::Interruptible)
+        .tuples()
+        .filter_map(|ev| ev.2)
+        .collect();
+
+    assert!(states.contains(&Status::Off));
+    assert!(!states.contains(&Status::Refresh));
+}
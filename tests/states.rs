@@ -0,0 +1,48 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::simulator::Time;
+use eee_hyst::stats::StateStats;
+use eee_hyst::switch::Status;
+
+#[test]
+fn isolated_packets() {
+    let input = vec![(1000, 1000), (100_000, 1000)];
+    let mut stats = StateStats::new();
+
+    for ev in setup(&input, Time(0), Time(0)) {
+        stats.record(&ev);
+    }
+
+    // The simulation ends with the last departure, before going back to LPI
+    assert_eq!(stats.wake_ups(), 2);
+    assert_eq!(stats.lpi_entries(), 1);
+    assert_eq!(
+        stats.all_transitions().collect::<Vec<_>>(),
+        vec![
+            (Status::On, Status::TOff, 1),
+            (Status::Off, Status::TOn, 2),
+            (Status::TOn, Status::On, 2),
+            (Status::TOff, Status::Off, 1),
+        ]
+    );
+    assert_eq!(stats.span(), Time(105_280));
+    assert_eq!(stats.time(Status::Off), Time(1000 + 100_000 - 9160));
+
+    let states: Vec<_> = stats.times().map(|(state, _)| state).collect();
+    assert_eq!(states, Status::ALL);
+    assert_eq!(
+        stats.times().map(|(_, time)| time.0).sum::<u64>(),
+        stats.span().0
+    );
+    assert_eq!(stats.time(Status::Refresh), Time(0));
+}
+
+#[test]
+fn no_events() {
+    let stats = StateStats::new();
+
+    assert_eq!(stats.span(), Time(0));
+    assert_eq!(stats.all_transitions().count(), 0);
+    assert_eq!(stats.wake_ups(), 0);
+}
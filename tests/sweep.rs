@@ -0,0 +1,32 @@
+use eee_hyst::sweep::{self, Values};
+
+#[test]
+fn parse_values() {
+    assert_eq!("5".parse(), Ok(Values::new(vec![5.0])));
+    assert_eq!(
+        "0,10:50:20".parse(),
+        Ok(Values::new(vec![0.0, 10.0, 30.0, 50.0]))
+    );
+    assert_eq!("0.1:0.5:0.2".parse(), Ok(Values::new(vec![0.1, 0.3, 0.5])));
+    assert_eq!("0:1:0.1".parse::<Values>().unwrap().len(), 11);
+}
+
+#[test]
+fn invalid_values() {
+    assert!("a".parse::<Values>().is_err());
+    assert!("1:2".parse::<Values>().is_err());
+    assert!("5:1:1".parse::<Values>().is_err());
+    assert!("0:5:0".parse::<Values>().is_err());
+}
+
+#[test]
+fn results_in_order() {
+    let jobs: Vec<u64> = (0..100).collect();
+
+    assert_eq!(
+        sweep::run(&jobs, 8, |job| job * job),
+        jobs.iter().map(|job| job * job).collect::<Vec<_>>()
+    );
+    assert_eq!(sweep::run(&jobs, 1, |job| job + 1)[99], 100);
+    assert!(sweep::run(&[] as &[u64], 4, |job| *job).is_empty());
+}
@@ -0,0 +1,49 @@
+use eee_hyst::simulator::Time;
+use eee_hyst::sweep::Values;
+
+#[test]
+fn parse_units() {
+    assert_eq!("4480".parse(), Ok(Time(4480)));
+    assert_eq!("4480ns".parse(), Ok(Time(4480)));
+    assert_eq!("10us".parse(), Ok(Time(10_000)));
+    assert_eq!("10µs".parse(), Ok(Time(10_000)));
+    assert_eq!("1.5ms".parse(), Ok(Time(1_500_000)));
+    assert_eq!("2s".parse(), Ok(Time(2_000_000_000)));
+    assert_eq!(" 0.1 us ".parse(), Ok(Time(100)));
+    assert_eq!("1e-3s".parse(), Ok(Time(1_000_000)));
+}
+
+#[test]
+fn parse_errors() {
+    for s in ["", "1.5", "-1us", "10 min", "us", "nan s", "1e30s"] {
+        assert!(s.parse::<Time>().is_err(), "{} should not parse", s);
+    }
+}
+
+#[test]
+fn display() {
+    assert_eq!(Time(0).to_string(), "0ns");
+    assert_eq!(Time(800).to_string(), "800ns");
+    assert_eq!(Time(4480).to_string(), "4.48us");
+    assert_eq!(Time(1_500_000).to_string(), "1.5ms");
+    assert_eq!(Time(2_000_000_001).to_string(), "2.000000001s");
+
+    for time in [Time(1), Time(4480), Time(39_680), Time(2_000_000_001)] {
+        assert_eq!(time.to_string().parse(), Ok(time));
+    }
+}
+
+#[test]
+fn sweep_time_ranges() {
+    assert_eq!(
+        "0:10us:2500ns".parse(),
+        Ok(Values::new(vec![
+            Time(0),
+            Time(2500),
+            Time(5000),
+            Time(7500),
+            Time(10_000)
+        ]))
+    );
+    assert!("0:1us:0".parse::<Values<Time>>().is_err());
+}
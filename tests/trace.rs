@@ -0,0 +1,154 @@
+use eee_hyst::simulator::Time;
+use eee_hyst::switch::Packet;
+use eee_hyst::trace::{self, TraceError, TraceReader, TraceSliceReader};
+use std::io::{self, BufReader, Read};
+
+#[test]
+fn comments_and_blank_lines() {
+    let input = "# time size\n\n1e-6\t1000\n  \n7e-6 1001 # second\n";
+    let packets: Vec<_> = TraceReader::new(input.as_bytes())
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+    assert_eq!(
+        packets,
+        vec![Packet::new(Time(1000), 1000), Packet::new(Time(7000), 1001)]
+    );
+}
+
+#[test]
+fn errors_have_line_numbers() {
+    let input = "1e-6 1000\nbad\n-1 64\n2e-6 big\n3e-6 64 1\n4e-6 64\n";
+    let results: Vec<_> = TraceReader::new(input.as_bytes()).collect();
+
+    assert_eq!(results.len(), 6);
+    assert!(matches!(&results[1], Err(TraceError::Malformed { line: 2, text }) if text == "bad"));
+    assert!(matches!(&results[2], Err(TraceError::InvalidTime { line: 3, text }) if text == "-1"));
+    assert!(matches!(&results[3], Err(TraceError::InvalidSize { line: 4, text }) if text == "big"));
+    assert!(matches!(
+        &results[4],
+        Err(TraceError::Malformed { line: 5, .. })
+    ));
+    assert_eq!(results[5].as_ref().unwrap(), &Packet::new(Time(4000), 64));
+    assert_eq!(
+        results[2].as_ref().unwrap_err().to_string(),
+        "line 3: -1 is not a valid arrival time"
+    );
+}
+
+#[test]
+fn backwards_arrival() {
+    let input = "1e-6 1000\n7e-6 1001\n5e-6 64\n8e-6 64\n";
+    let results: Vec<_> = TraceReader::new(input.as_bytes()).collect();
+
+    assert!(matches!(
+        results[2],
+        Err(TraceError::Backwards {
+            line: 3,
+            arrival: Time(5000),
+            previous: Time(7000)
+        })
+    ));
+    assert_eq!(results[3].as_ref().unwrap(), &Packet::new(Time(8000), 64));
+}
+
+struct Failing;
+
+impl Read for Failing {
+    fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::other("broken pipe"))
+    }
+}
+
+#[test]
+fn io_errors_end_the_trace() {
+    let input = "1e-6 1000\n".as_bytes().chain(BufReader::new(Failing));
+    let mut reader = TraceReader::new(BufReader::new(input));
+
+    assert!(reader.next().unwrap().is_ok());
+    assert!(matches!(
+        reader.next(),
+        Some(Err(TraceError::Io { line: 2, .. }))
+    ));
+    assert!(reader.next().is_none());
+}
+
+#[test]
+fn same_times_as_floating_point() {
+    let times = [
+        "0",
+        "0.0",
+        "1",
+        "1.",
+        ".5",
+        "0.000001",
+        "1e-6",
+        "1E-6",
+        "4.48e-6",
+        "1.5e+3",
+        "0.1234567894",
+        "0.1234567895",
+        "0.0000000005",
+        "0.30000000000000004",
+        "123456.789012345",
+        "999999.999999999",
+        "1000000.000000001",
+        "1e6",
+        "1e9",
+        "1e20",
+        "12345678901234567890",
+        "00000000000000000000001.5",
+        "1.000000000000000000000",
+        "5e-10",
+        "2.5e-9",
+        "1e-400",
+        "0e999",
+        "inf",
+        "nan",
+        "-0",
+        "-1",
+        "+1",
+        "1e",
+        "e5",
+        ".",
+        "1..2",
+        "1e5x",
+        "0x10",
+    ];
+
+    for time in times {
+        let expected = time
+            .parse::<f64>()
+            .ok()
+            .filter(|secs| secs.is_finite() && *secs >= 0.0)
+            .map(Time::from_secs);
+        assert_eq!(trace::parse_secs(time.as_bytes()), expected, "{}", time);
+    }
+
+    for ns in (0..1_000_000_000_000_000u64).step_by(999_999_999_937) {
+        let time = format!("{:e}", Time(ns).as_secs());
+        assert_eq!(
+            trace::parse_secs(time.as_bytes()),
+            Some(Time(ns)),
+            "{}",
+            time
+        );
+    }
+}
+
+#[test]
+fn slice_reader_matches_buffered_reader() {
+    let input =
+        "# trace\n1e-6 1000\r\n\n2.5e-6\t64 # c\nbad\n1e-6 64\n3e-6 x\n4e-6\u{a0}64\n5e-6 1500";
+    let buffered: Vec<_> = TraceReader::new(input.as_bytes())
+        .map(|packet| packet.map_err(|err| err.to_string()))
+        .collect();
+    let slice: Vec<_> = TraceSliceReader::new(input.as_bytes())
+        .map(|packet| packet.map_err(|err| err.to_string()))
+        .collect();
+
+    assert_eq!(buffered, slice);
+    assert_eq!(buffered.len(), 7);
+    assert_eq!(buffered[5], Ok(Packet::new(Time(4000), 64)));
+    assert_eq!(buffered[6], Ok(Packet::new(Time(5000), 1500)));
+}
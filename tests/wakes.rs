@@ -0,0 +1,64 @@
+mod common;
+
+use crate::common::*;
+use eee_hyst::simulator::{Simulator, Time};
+use eee_hyst::stats::{FlowWakes, WakeStats, WakeUp};
+use eee_hyst::switch::Packet;
+
+fn wakes(packets: Vec<Packet>) -> WakeStats {
+    let mut wakes = WakeStats::new();
+    for ev in Simulator::new(Time(0), Time(0), packets.into_iter()) {
+        wakes.record(&ev);
+    }
+
+    wakes
+}
+
+#[test]
+fn wake_causes() {
+    let input = vec![(1000, 1000), (2000, 64), (100_000, 1000)];
+    let packets: Vec<_> = packets(&input).collect();
+    let wakes = wakes(packets.clone());
+
+    assert_eq!(
+        wakes.wakes(),
+        &[
+            WakeUp {
+                time: Time(1000),
+                cause: packets[0],
+                packets: 2,
+            },
+            WakeUp {
+                time: Time(100_000),
+                cause: packets[2],
+                packets: 1,
+            },
+        ]
+    );
+    assert_eq!(wakes.mean_packets(), Some(1.5));
+}
+
+#[test]
+fn wakes_by_flow() {
+    let input = vec![(1000, 1000), (2000, 64), (100_000, 1000), (200_000, 64)];
+    let packets: Vec<_> = packets(&input)
+        .zip([Some(1), Some(0), Some(1), None])
+        .map(|(packet, flow)| flow.map_or(packet, |flow| packet.with_flow(flow)))
+        .collect();
+
+    assert_eq!(
+        wakes(packets).by_flow(),
+        vec![
+            FlowWakes {
+                flow: None,
+                wakes: 1,
+                packets: 1,
+            },
+            FlowWakes {
+                flow: Some(1),
+                wakes: 2,
+                packets: 3,
+            },
+        ]
+    );
+}